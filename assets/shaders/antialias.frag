@@ -0,0 +1,104 @@
+#version 450
+
+// Resolves the scene target onto the window and optionally smooths its edges with FXAA.
+// Compiled twice, the second time with MULTISAMPLE defined for multisampled scene targets:
+//
+//     glslc antialias.frag -o antialias.frag.spv
+//     glslc -DMULTISAMPLE antialias.frag -o antialias_ms.frag.spv
+
+#ifdef MULTISAMPLE
+layout(set = 0, binding = 0) uniform sampler2DMS scene;
+#else
+layout(set = 0, binding = 0) uniform sampler2D scene;
+#endif
+
+layout(push_constant) uniform AntialiasArgs {
+    vec2 inverse_size;
+    int samples;
+    int fxaa;
+};
+
+layout(location = 0) in vec2 tex_coord;
+
+layout(location = 0) out vec4 out_color;
+
+const float EDGE_THRESHOLD = 0.125;
+const float EDGE_THRESHOLD_MIN = 0.0312;
+const float SUBPIXEL_QUALITY = 0.75;
+
+// The scene color at a pixel, averaged over its samples. Colors are premultiplied by alpha
+// as the scene is cleared to transparent.
+vec4 resolve(ivec2 pixel) {
+    ivec2 size = ivec2(round(1.0 / inverse_size));
+    pixel = clamp(pixel, ivec2(0), size - 1);
+#ifdef MULTISAMPLE
+    vec4 color = vec4(0.0);
+    for (int i = 0; i < samples; i++) {
+        color += texelFetch(scene, pixel, i);
+    }
+    return color / float(samples);
+#else
+    return texelFetch(scene, pixel, 0);
+#endif
+}
+
+// Bilinear filtering of the resolved scene, which a multisampled image cannot do itself.
+vec4 resolve_linear(vec2 position) {
+    vec2 texel = position - 0.5;
+    ivec2 base = ivec2(floor(texel));
+    vec2 f = fract(texel);
+    vec4 bottom = mix(resolve(base), resolve(base + ivec2(1, 0)), f.x);
+    vec4 top = mix(resolve(base + ivec2(0, 1)), resolve(base + ivec2(1, 1)), f.x);
+    return mix(bottom, top, f.y);
+}
+
+float luma(vec4 color) {
+    return dot(color.rgb, vec3(0.299, 0.587, 0.114));
+}
+
+// A single-step FXAA: finds the direction of the strongest local luma edge and blends
+// across it in proportion to the local contrast.
+vec4 fxaa_color(ivec2 pixel) {
+    vec4 center = resolve(pixel);
+    float l = luma(center);
+    float n = luma(resolve(pixel + ivec2(0, 1)));
+    float s = luma(resolve(pixel + ivec2(0, -1)));
+    float e = luma(resolve(pixel + ivec2(1, 0)));
+    float w = luma(resolve(pixel + ivec2(-1, 0)));
+
+    float lowest = min(l, min(min(n, s), min(e, w)));
+    float highest = max(l, max(max(n, s), max(e, w)));
+    float range = highest - lowest;
+    if (range < max(EDGE_THRESHOLD_MIN, highest * EDGE_THRESHOLD)) {
+        return center;
+    }
+
+    float ne = luma(resolve(pixel + ivec2(1, 1)));
+    float nw = luma(resolve(pixel + ivec2(-1, 1)));
+    float se = luma(resolve(pixel + ivec2(1, -1)));
+    float sw = luma(resolve(pixel + ivec2(-1, -1)));
+
+    float horizontal = abs(nw + sw - 2.0 * w) + 2.0 * abs(n + s - 2.0 * l) + abs(ne + se - 2.0 * e);
+    float vertical = abs(nw + ne - 2.0 * n) + 2.0 * abs(w + e - 2.0 * l) + abs(sw + se - 2.0 * s);
+    bool is_horizontal = horizontal >= vertical;
+
+    // Step towards the neighbor across the edge with the larger gradient.
+    float positive = is_horizontal ? n : e;
+    float negative = is_horizontal ? s : w;
+    vec2 step_dir = is_horizontal ? vec2(0.0, 1.0) : vec2(1.0, 0.0);
+    if (abs(negative - l) > abs(positive - l)) {
+        step_dir = -step_dir;
+    }
+
+    float average = (2.0 * (n + s + e + w) + ne + nw + se + sw) / 12.0;
+    float subpixel = clamp(abs(average - l) / range, 0.0, 1.0);
+    subpixel = smoothstep(0.0, 1.0, subpixel);
+    float blend = subpixel * subpixel * SUBPIXEL_QUALITY * 0.5;
+
+    return resolve_linear(vec2(pixel) + 0.5 + step_dir * blend);
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    out_color = fxaa != 0 ? fxaa_color(pixel) : resolve(pixel);
+}
@@ -0,0 +1,21 @@
+// Squash and stretch, see `Deformation` in src/squash.rs.
+// motion.xyz: unit direction of motion, motion.w: stretch factor along it.
+// smear.xyz: world space center, smear.w: factor the trailing half's distance behind the
+// center is lengthened by.
+
+vec3 deform_position(vec3 position, vec4 motion, vec4 smear) {
+    vec3 rel = position - smear.xyz;
+    float along = dot(rel, motion.xyz);
+    vec3 across = rel - along * motion.xyz;
+    // Volume preserving: stretch by s along the motion, squash by 1/sqrt(s) across it.
+    rel = across / sqrt(motion.w) + along * motion.w * motion.xyz;
+    // Smear: drag the trailing half back along the path it came from.
+    rel += motion.xyz * min(along, 0.0) * smear.w;
+    return smear.xyz + rel;
+}
+
+vec3 deform_normal(vec3 normal, vec4 motion) {
+    // Inverse transpose of the stretch above; the fragment shader renormalizes.
+    float along = dot(normal, motion.xyz);
+    return (normal - along * motion.xyz) * sqrt(motion.w) + along / motion.w * motion.xyz;
+}
@@ -41,6 +41,26 @@ float ggx_geometry(float NdotV, float NdotL, float r2) {
     return ggx1 * ggx2;
 }
 
+// Hash of a 3D cell in [0, 1], used by value_noise.
+float hash13(vec3 p) {
+    p = fract(p * 0.1031);
+    p += dot(p, p.zyx + 31.32);
+    return fract((p.x + p.y) * p.z);
+}
+
+// Smooth 3D value noise in [-1, 1].
+float value_noise(vec3 p) {
+    vec3 cell = floor(p);
+    vec3 t = fract(p);
+    t = t * t * (3.0 - 2.0 * t);
+    return mix(
+        mix(mix(hash13(cell), hash13(cell + vec3(1, 0, 0)), t.x),
+            mix(hash13(cell + vec3(0, 1, 0)), hash13(cell + vec3(1, 1, 0)), t.x), t.y),
+        mix(mix(hash13(cell + vec3(0, 0, 1)), hash13(cell + vec3(1, 0, 1)), t.x),
+            mix(hash13(cell + vec3(0, 1, 1)), hash13(cell + vec3(1, 1, 1)), t.x), t.y),
+        t.z) * 2.0 - 1.0;
+}
+
 float s_curve (float x) {
 		x = x * 2.0 - 1.0;
 		return -x * abs(x) * 0.5 + x + 0.5;
@@ -0,0 +1,36 @@
+#version 450
+
+#include "header/deform.vert"
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 tex_coord;
+layout(location = 3) in mat4 model; // instance rate
+layout(location = 7) in vec4 tint; // instance rate
+layout(location = 8) in vec4 motion; // instance rate
+layout(location = 9) in vec4 smear; // instance rate
+layout(location = 10) in float band_softness; // instance rate
+
+layout(location = 0) out VertexData {
+    vec3 position;
+    vec3 normal;
+    vec2 tex_coord;
+    vec4 color;
+    float band_softness;
+} vertex;
+
+void main() {
+    vec4 vertex_position = model * vec4(position, 1.0);
+    vertex.position = deform_position(vertex_position.xyz, motion, smear);
+    vertex.normal = deform_normal(mat3(model) * normal, motion);
+    vertex.tex_coord = tex_coord;
+    vertex.color = tint;
+    vertex.band_softness = band_softness;
+    gl_Position = proj_view * vec4(vertex.position, 1.0);
+}
@@ -0,0 +1,53 @@
+#version 450
+
+#include "header/math.frag"
+
+#include "header/environment.frag"
+
+// Keep in sync with NprUniformArgs in src/custom_render.rs
+layout(std140, set = 3, binding = 0) uniform NprUniformArgs {
+    float boil_seed;
+    float boil_amplitude;
+    float boil_frequency;
+    vec3 ortho_view_direction;
+    float orthographic;
+    int debug_view;
+    vec2 depth_range;
+    vec4 band_thresholds;
+    vec4 band_values;
+    vec4 band_saturation;
+    int band_count;
+    float outline_threshold;
+    float outline_opacity;
+    int palette_size;
+    vec4 outline_color;
+    vec4 palette[8];
+    float band_softness;
+};
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec3 normal;
+    vec2 tex_coord;
+    vec4 color;
+    float band_softness;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+// Onion skin ghosts: only the outline band of the cel shader, in the ghost's tint.
+void main() {
+    vec3 normal = normalize(vertex.normal);
+    // Orthographic cameras see everything from the same direction.
+    vec3 view_direction = orthographic > 0.5
+        ? ortho_view_direction
+        : normalize(camera_position - vertex.position);
+
+    float wobble = boil_amplitude * value_noise(vertex.position * boil_frequency + vec3(boil_seed * 17.0));
+
+    if (dot(normal, view_direction) > outline_threshold + wobble) {
+        discard;
+    }
+    // Premultiplied alpha
+    out_color = vec4(vertex.color.rgb * vertex.color.a, vertex.color.a);
+}
@@ -9,6 +9,38 @@ layout(set = 1, binding = 0) uniform Material {
     float alpha_cutoff;
 };
 
+// Keep in sync with NprUniformArgs in src/custom_render.rs
+layout(std140, set = 3, binding = 0) uniform NprUniformArgs {
+    float boil_seed;
+    float boil_amplitude;
+    float boil_frequency;
+    vec3 ortho_view_direction;
+    float orthographic;
+    int debug_view;
+    vec2 depth_range;
+    vec4 band_thresholds;
+    vec4 band_values;
+    vec4 band_saturation;
+    int band_count;
+    float outline_threshold;
+    float outline_opacity;
+    int palette_size;
+    vec4 outline_color;
+    vec4 palette[8];
+    float band_softness;
+};
+
+// Keep in sync with DebugView in src/debug_view.rs
+const int DEBUG_OFF = 0;
+const int DEBUG_NORMALS = 1;
+const int DEBUG_LIGHTING = 2;
+const int DEBUG_BANDS = 3;
+const int DEBUG_ALBEDO = 4;
+const int DEBUG_RIM = 5;
+const int DEBUG_DEPTH = 6;
+const int DEBUG_UV_CHECKER = 7;
+const int DEBUG_LIGHT_COUNT = 8;
+
 layout(set = 1, binding = 1) uniform sampler2D albedo;
 layout(set = 1, binding = 2) uniform sampler2D emission;
 
@@ -17,6 +49,7 @@ layout(location = 0) in VertexData {
     vec3 normal;
     vec2 tex_coord;
     vec4 color;
+    float band_softness;
 } vertex;
 
 layout(location = 0) out vec4 out_color;
@@ -47,30 +80,55 @@ vec3 hsb2rgb( in vec3 c ){
 }
 
 
-vec3 cel_shading ( vec3 color ) {
+// Quantizes the brightness of `color` into the style's light bands. With a band softness,
+// neighboring bands are blended over that many pixels, measured by `brightness_width`, the
+// `fwidth` of the brightness, so the edge stays the same width on screen at any resolution
+// or distance.
+vec3 cel_shading ( vec3 color, float brightness_width ) {
+    if (band_count == 0) {
+        return color;
+    }
     vec3 color_hsb = rgb2hsb(color);
-    if (color_hsb.z <= 0.5) {
-        color_hsb.z = 0.2;
-    } else if (color_hsb.z <= 0.55) {
-        color_hsb.z = 0.8;
-        color_hsb.y = min(color_hsb.y + 0.7, 1.0);
-    } else {
-        color_hsb.z = 0.8;
+    float brightness = color_hsb.z;
+    float edge_width = brightness_width * band_softness * vertex.band_softness;
+    float value = band_values[0];
+    float saturation = band_saturation[0];
+    for (int i = 0; i < band_count - 1; i++) {
+        float threshold = band_thresholds[i];
+        float weight = edge_width > 0.0
+            ? smoothstep(threshold - 0.5 * edge_width, threshold + 0.5 * edge_width, brightness)
+            : float(brightness > threshold);
+        value = mix(value, band_values[i + 1], weight);
+        saturation = mix(saturation, band_saturation[i + 1], weight);
     }
+    color_hsb.z = value;
+    color_hsb.y = min(color_hsb.y + saturation, 1.0);
     return hsb2rgb(color_hsb);
 }
 
+// Snaps `color` to the nearest palette color, if the style has a palette.
+vec3 apply_palette ( vec3 color ) {
+    if (palette_size == 0) {
+        return color;
+    }
+    vec3 nearest = palette[0].rgb;
+    for (int i = 1; i < palette_size; i++) {
+        if (distance(color, palette[i].rgb) < distance(color, nearest)) {
+            nearest = palette[i].rgb;
+        }
+    }
+    return nearest;
+}
 
-void main() {
-    vec2 final_tex_coords   = tex_coords(vertex.tex_coord, uv_offset);
-    vec4 albedo_alpha       = texture(albedo, final_tex_coords);
-    float alpha             = albedo_alpha.a;
-    if(alpha < alpha_cutoff) discard;
+// Blue through green to red as t goes from 0 to 1.
+vec3 heatmap(float t) {
+    return clamp(vec3(2.0 * t - 1.0, 1.0 - abs(2.0 * t - 1.0), 1.0 - 2.0 * t), 0.0, 1.0);
+}
 
-    vec3 albedo = albedo_alpha.rgb;
-    vec3 emission = texture(emission, final_tex_coords).rgb;
 
+void main() {
     vec3 lighting = vec3(0.0);
+    uint light_count = 0u;
     vec3 normal = normalize(vertex.normal);
     for (uint i = 0u; i < point_light_count; i++) {
         // Calculate diffuse light
@@ -82,33 +140,93 @@ void main() {
         float dist2 = dot(dist, dist);
         float attenuation = (plight[i].intensity / dist2);
         lighting += diffuse * attenuation;
+        if (diff * attenuation > 0.01) light_count++;
     }
     for (uint i = 0u; i < directional_light_count; i++) {
         vec3 dir = dlight[i].direction;
         float diff = max(dot(-dir, normal), 0.0);
         vec3 diffuse = diff * dlight[i].color;
         lighting += diffuse * dlight[i].intensity;
+        if (diff > 0.0) light_count++;
     }
-    lighting = cel_shading(lighting);
+    vec3 raw_lighting = lighting;
+    // Derivatives are undefined once neighboring fragments are discarded, so take the
+    // brightness width for the band edges before the alpha cutoff.
+    float brightness_width = fwidth(rgb2hsb(raw_lighting).z);
+
+    vec2 final_tex_coords   = tex_coords(vertex.tex_coord, uv_offset);
+    vec4 albedo_alpha       = texture(albedo, final_tex_coords);
+    float alpha             = albedo_alpha.a;
+    if(alpha < alpha_cutoff) discard;
+
+    vec3 albedo = albedo_alpha.rgb;
+    vec3 emission = texture(emission, final_tex_coords).rgb;
+
+    lighting = cel_shading(lighting, brightness_width);
     lighting += ambient_color;
 
-    vec3 view_direction = normalize(camera_position - vertex.position);
+    // Orthographic cameras see everything from the same direction.
+    vec3 view_direction = orthographic > 0.5
+        ? ortho_view_direction
+        : normalize(camera_position - vertex.position);
 
-    vec4 outline_color = vec4(0.0, 0.0, 0.0, 1.0);
-    float outline_opacity = 0.0;
     vec4 colorModifier = vec4(0.1, 0.2, 0.2, 1.0 - outline_opacity);
 
-    vec4 final_light = vec4(lighting * albedo + emission, alpha);
+    vec4 final_light = vec4(apply_palette(lighting * albedo + emission), alpha);
 
     vec4 model_color = final_light * vertex.color;
 
     float dx = 1.0 / 1024.0;
     float dy = 1.0 / 768.0;
 
-    if (dot(normal, view_direction) <= 0.15) {
+    // Line boil: the outline threshold is re-traced with new noise every time the seed changes.
+    float wobble = boil_amplitude * value_noise(vertex.position * boil_frequency + vec3(boil_seed * 17.0));
+
+    bool rim = dot(normal, view_direction) <= outline_threshold + wobble;
+
+    // Debug views replace the shading, branching on a uniform so they switch without new pipelines.
+    if (debug_view != DEBUG_OFF) {
+        vec3 debug_color = vec3(1.0, 0.0, 1.0);
+        switch (debug_view) {
+            case DEBUG_NORMALS:
+                debug_color = normal * 0.5 + 0.5;
+                break;
+            case DEBUG_LIGHTING:
+                debug_color = raw_lighting;
+                break;
+            case DEBUG_BANDS:
+                debug_color = cel_shading(raw_lighting, brightness_width);
+                break;
+            case DEBUG_ALBEDO:
+                debug_color = albedo * vertex.color.rgb;
+                break;
+            case DEBUG_RIM:
+                debug_color = vec3(rim ? 1.0 : 0.0);
+                break;
+            case DEBUG_DEPTH:
+                float depth = (distance(camera_position, vertex.position) - depth_range.x)
+                    / max(depth_range.y - depth_range.x, 1e-6);
+                debug_color = vec3(1.0 - clamp(depth, 0.0, 1.0));
+                break;
+            case DEBUG_UV_CHECKER:
+                vec2 cell = floor(vertex.tex_coord * 8.0);
+                float checker = mod(cell.x + cell.y, 2.0);
+                debug_color = mix(vec3(0.2), vec3(0.9), checker) * vec3(vertex.tex_coord, 1.0);
+                break;
+            case DEBUG_LIGHT_COUNT:
+                uint light_total = max(point_light_count + directional_light_count, 1u);
+                debug_color = heatmap(float(light_count) / float(light_total));
+                break;
+        }
+        out_color = vec4(debug_color, 1.0);
+        return;
+    }
+
+    if (rim) {
         out_color = mix(outline_color, model_color, outline_opacity);
         //out_color = vec4(lighting * albedo + emission, alpha) * vertex.color * colorModifier;
     } else {
         out_color = model_color;
         //discard;
     }
+}
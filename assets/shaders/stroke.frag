@@ -0,0 +1,19 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform sampler2D brush;
+
+layout(location = 0) in VertexData {
+    vec2 tex_coord;
+    vec4 color;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // Brush textures are greyscale masks; white is full paint coverage.
+    vec4 mask = texture(brush, vertex.tex_coord);
+    float coverage = mask.r * mask.a;
+    if (coverage * vertex.color.a <= 0.01) discard;
+
+    out_color = vec4(vertex.color.rgb, vertex.color.a * coverage);
+}
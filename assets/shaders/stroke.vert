@@ -0,0 +1,20 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform StrokeUniformArgs {
+    uniform mat4 proj_view;
+};
+
+layout(location = 0) in vec3 pos;
+layout(location = 1) in vec2 tex_coord;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out VertexData {
+    vec2 tex_coord;
+    vec4 color;
+} vertex;
+
+void main() {
+    vertex.tex_coord = tex_coord;
+    vertex.color = color;
+    gl_Position = proj_view * vec4(pos, 1.0);
+}
@@ -0,0 +1,716 @@
+use amethyst::{
+    animation::{
+        Animation, AnimationCommand, AnimationControlSet, AnimationSet, ControlState,
+        EndControl, Sampler, SamplerPrimitive, StepDirection,
+    },
+    assets::AssetStorage,
+    core::{
+        ecs::{Component, DenseVecStorage, Join, Read, ReadStorage, System, WriteStorage},
+        Time, Transform,
+    },
+    derive::PrefabData,
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+/// Speed multipliers stepped through by `PlaybackCommand::Faster` and `Slower`.
+const SPEEDS: [f32; 7] = [0.125, 0.25, 0.5, 1.0, 1.5, 2.0, 4.0];
+
+/// Holds each sampled pose of an entity's animations for several frames, like hand-drawn
+/// animation done "on twos" or "on threes", while the rest of the scene updates every frame.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct AnimationStep {
+    /// Number of animation frames each pose is held for: 1 (ones), 2 (twos) or 3 (threes).
+    pub frames: u32,
+    /// Frame rate the steps are counted at, 24 for film.
+    pub frame_rate: f32,
+    /// Playback speed applied on top of the stepping.
+    pub speed: f32,
+    #[serde(skip)]
+    accumulator: f32,
+}
+
+impl Default for AnimationStep {
+    fn default() -> Self {
+        AnimationStep {
+            frames: 1,
+            frame_rate: 24.0,
+            speed: 1.0,
+            accumulator: 0.0,
+        }
+    }
+}
+
+impl Component for AnimationStep {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl AnimationStep {
+    /// Cycles between ones, twos and threes.
+    pub fn cycle(&mut self) {
+        self.frames = self.frames % 3 + 1;
+        self.accumulator = 0.0;
+    }
+
+    /// Rate multiplier to apply to the animation this frame, given the frame's delta time.
+    ///
+    /// Held frames return zero; the frame completing a step returns whatever rate advances
+    /// the sampler by the time accumulated since the previous pose.
+    pub fn rate(&mut self, delta_seconds: f32) -> f32 {
+        if self.frames <= 1 || delta_seconds <= 0.0 {
+            return self.speed;
+        }
+        let step = self.frames as f32 / self.frame_rate.max(1.0);
+        self.accumulator += delta_seconds;
+        if self.accumulator < step {
+            0.0
+        } else {
+            let elapsed = self.accumulator - self.accumulator % step;
+            self.accumulator -= elapsed;
+            self.speed * elapsed / delta_seconds
+        }
+    }
+}
+
+/// Applies `AnimationStep` to the running animations of each entity.
+///
+/// Must run before the `AnimationBundle`'s control system so that rates reach the samplers
+/// in the same frame.
+#[derive(Debug, Default)]
+pub struct AnimationStepSystem;
+
+impl<'a> System<'a> for AnimationStepSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        ReadStorage<'a, ManualClock>,
+        WriteStorage<'a, AnimationStep>,
+        WriteStorage<'a, AnimationControlSet<usize, Transform>>,
+    );
+
+    fn run(&mut self, (time, manual, mut steps, mut controls): Self::SystemData) {
+        for (step, set, _) in (&mut steps, &mut controls, !&manual).join() {
+            let rate = step.rate(time.delta_seconds());
+            let running = set
+                .animations
+                .iter()
+                .filter(|(_, control)| matches!(control.state, ControlState::Running(_)))
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+            for id in running {
+                set.set_rate(id, rate);
+            }
+        }
+    }
+}
+
+/// Poses an entity's clip at an exact time instead of playing it with the frame clock,
+/// for scrubbing through an animation or rendering stills at precise poses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ManualClock {
+    /// The clip being posed.
+    pub clip: usize,
+    time: f32,
+    dirty: bool,
+}
+
+impl Component for ManualClock {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl ManualClock {
+    pub fn new(clip: usize, time: f32) -> Self {
+        ManualClock {
+            clip,
+            time: time.max(0.0),
+            dirty: true,
+        }
+    }
+
+    /// Time in seconds the clip is posed at.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+        self.dirty = true;
+    }
+
+    /// Moves the clock by `delta` seconds; wraps around the clip's length once applied.
+    pub fn scrub(&mut self, delta: f32) {
+        self.set_time(self.time + delta);
+    }
+
+    /// Whether the current time has been applied to the clip's samplers.
+    pub fn is_posed(&self) -> bool {
+        !self.dirty
+    }
+
+    /// Poses clip `clip` from the start.
+    pub fn set_clip(&mut self, clip: usize) {
+        self.clip = clip;
+        self.set_time(0.0);
+    }
+}
+
+/// Length of a clip in seconds: the last keyframe of its longest sampler.
+pub fn clip_duration(
+    animation: &Animation<Transform>,
+    samplers: &AssetStorage<Sampler<SamplerPrimitive<f32>>>,
+) -> f32 {
+    animation
+        .nodes
+        .iter()
+        .filter_map(|(_, _, sampler)| samplers.get(sampler))
+        .filter_map(|sampler| sampler.input.last().cloned())
+        .fold(0.0, f32::max)
+}
+
+/// Freezes clips driven by a `ManualClock` and sets their sampler input to its time.
+///
+/// Must run before the `AnimationBundle`'s control system, like `AnimationStepSystem`.
+#[derive(Debug, Default)]
+pub struct ManualClockSystem;
+
+impl<'a> System<'a> for ManualClockSystem {
+    type SystemData = (
+        Read<'a, AssetStorage<Animation<Transform>>>,
+        Read<'a, AssetStorage<Sampler<SamplerPrimitive<f32>>>>,
+        ReadStorage<'a, AnimationSet<usize, Transform>>,
+        WriteStorage<'a, ManualClock>,
+        WriteStorage<'a, AnimationControlSet<usize, Transform>>,
+    );
+
+    fn run(&mut self, (animations, samplers, sets, mut clocks, mut controls): Self::SystemData) {
+        for (clock, set, controls) in (&mut clocks, &sets, &mut controls).join() {
+            if !clock.dirty {
+                continue;
+            }
+            let clip = clock.clip;
+            let running = controls.animations.iter().any(|(id, control)| {
+                *id == clip && matches!(control.state, ControlState::Running(_))
+            });
+            // Keep the request pending until the clip has actually started.
+            if !running {
+                continue;
+            }
+            let duration = set
+                .animations
+                .get(&clip)
+                .and_then(|handle| animations.get(handle))
+                .map(|animation| clip_duration(animation, &samplers))
+                .unwrap_or(0.0);
+            if duration > 0.0 {
+                clock.time = clock.time.rem_euclid(duration);
+            }
+            controls.set_rate(clip, 0.0);
+            controls.set_input(clip, clock.time);
+            clock.dirty = false;
+        }
+    }
+}
+
+/// Cross-fades between an entity's clips instead of snapping to the new one.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct CrossFade {
+    /// Seconds over which the outgoing clip's weight falls to zero, 0 to switch instantly.
+    pub duration: f32,
+    #[serde(skip)]
+    active: Option<Fade>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fade {
+    from: usize,
+    to: usize,
+    elapsed: f32,
+}
+
+impl Default for CrossFade {
+    fn default() -> Self {
+        CrossFade {
+            duration: 0.3,
+            active: None,
+        }
+    }
+}
+
+impl Component for CrossFade {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl CrossFade {
+    /// Starts fading from clip `from` to clip `to`.
+    ///
+    /// Returns the clip that should be stopped right away: the outgoing clip of a fade still
+    /// in progress, or `from` itself when fading is disabled.
+    pub fn start(&mut self, from: usize, to: usize) -> Option<usize> {
+        if self.duration <= 0.0 {
+            return Some(from);
+        }
+        let interrupted = self
+            .active
+            .map(|fade| fade.from)
+            .filter(|&old| old != from && old != to);
+        self.active = Some(Fade {
+            from,
+            to,
+            elapsed: 0.0,
+        });
+        interrupted
+    }
+}
+
+/// Blend weight of the outgoing clip until the incoming one has started.
+///
+/// amethyst_animation's `SamplerInterpolationSystem` divides the weight of every sampler by
+/// the total weight on its channel, so this outweighs the incoming clip's default of 1.0 and
+/// keeps its first sampled frame from popping in at half strength.
+const HOLD_WEIGHT: f32 = 1.0e3;
+
+/// Sets the blend weights of every sampler of `animation` on `controls`.
+fn set_clip_weight(
+    controls: &mut AnimationControlSet<usize, Transform>,
+    id: usize,
+    animation: &Animation<Transform>,
+    weight: f32,
+) {
+    let weights = animation
+        .nodes
+        .iter()
+        .map(|(node, channel, _)| (*node, channel.clone(), weight))
+        .collect();
+    controls.set_blend_weight(id, weights);
+}
+
+/// Advances `CrossFade`s, weighting the outgoing and incoming clips together and stopping
+/// the outgoing one once the fade completes.
+///
+/// Must run before the `AnimationBundle`'s control system, like `AnimationStepSystem`.
+#[derive(Debug, Default)]
+pub struct CrossFadeSystem;
+
+impl<'a> System<'a> for CrossFadeSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, AssetStorage<Animation<Transform>>>,
+        ReadStorage<'a, AnimationSet<usize, Transform>>,
+        WriteStorage<'a, CrossFade>,
+        WriteStorage<'a, AnimationControlSet<usize, Transform>>,
+    );
+
+    fn run(&mut self, (time, storage, sets, mut fades, mut controls): Self::SystemData) {
+        for (crossfade, set, controls) in (&mut fades, &sets, &mut controls).join() {
+            let duration = crossfade.duration;
+            let fade = match crossfade.active.as_mut() {
+                Some(fade) => fade,
+                None => continue,
+            };
+            let running = |id: usize| {
+                controls.animations.iter().any(|(running, control)| {
+                    *running == id && matches!(control.state, ControlState::Running(_))
+                })
+            };
+            // Blend weights can only be set once the incoming clip has started, otherwise
+            // the command would replace its pending start.
+            if !running(fade.to) {
+                let from = fade.from;
+                if let Some(animation) = set.animations.get(&from).and_then(|h| storage.get(h)) {
+                    set_clip_weight(controls, from, animation, HOLD_WEIGHT);
+                }
+                continue;
+            }
+            fade.elapsed += time.delta_seconds();
+            let t = (fade.elapsed / duration).min(1.0);
+            let (from, to) = (fade.from, fade.to);
+
+            if t >= 1.0 || !running(from) {
+                controls.abort(from);
+                if let Some(animation) = set.animations.get(&to).and_then(|h| storage.get(h)) {
+                    set_clip_weight(controls, to, animation, 1.0);
+                }
+                crossfade.active = None;
+                continue;
+            }
+            // The incoming clip starts at a tiny fraction rather than zero to keep the
+            // normalizing total non-zero.
+            if let Some(animation) = set.animations.get(&from).and_then(|h| storage.get(h)) {
+                set_clip_weight(controls, from, animation, 1.0 - t);
+            }
+            if let Some(animation) = set.animations.get(&to).and_then(|h| storage.get(h)) {
+                set_clip_weight(controls, to, animation, t.max(1.0e-3));
+            }
+        }
+    }
+}
+
+/// Names of an entity's animation clips, read from its glTF file.
+///
+/// Indices match the ids `GltfSceneFormat` gives the clips in the entity's `AnimationSet`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct AnimationClips {
+    /// glTF path relative to the assets directory.
+    pub model: String,
+    #[serde(skip)]
+    pub names: Vec<String>,
+}
+
+impl Component for AnimationClips {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl AnimationClips {
+    /// Reads the clip names from the model, naming unnamed clips after their index.
+    pub fn load_names(&mut self, assets_dir: &Path) -> Result<(), Error> {
+        let gltf = gltf::Gltf::open(assets_dir.join(&self.model)).map_err(Error::new)?;
+        self.names = gltf
+            .animations()
+            .map(|animation| match animation.name() {
+                Some(name) => name.to_string(),
+                None => format!("animation {}", animation.index()),
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Id of the clip called `name`, ignoring case.
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.names
+            .iter()
+            .position(|clip| clip.eq_ignore_ascii_case(name))
+    }
+
+    /// Name of clip `id`, falling back to its index for models without names.
+    pub fn name(&self, id: usize) -> String {
+        self.names
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| format!("animation {}", id))
+    }
+}
+
+/// A playback control, usually triggered by an input action of the same name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaybackCommand {
+    /// Start clip `id` from the beginning, stopping the current one.
+    Play(usize),
+    PlayPause,
+    Restart,
+    Next,
+    Previous,
+    Faster,
+    Slower,
+    StepForward,
+    StepBackward,
+}
+
+impl PlaybackCommand {
+    /// Maps an input action from `config/input.ron` to its command.
+    pub fn from_action(action: &str) -> Option<Self> {
+        Some(match action {
+            "anim_play_pause" => PlaybackCommand::PlayPause,
+            "anim_restart" => PlaybackCommand::Restart,
+            "anim_next" => PlaybackCommand::Next,
+            "anim_previous" => PlaybackCommand::Previous,
+            "anim_faster" => PlaybackCommand::Faster,
+            "anim_slower" => PlaybackCommand::Slower,
+            "anim_step_forward" => PlaybackCommand::StepForward,
+            "anim_step_backward" => PlaybackCommand::StepBackward,
+            _ => return None,
+        })
+    }
+}
+
+/// Plays clip `id`, which must already be in `controls`, from the beginning.
+///
+/// A control holds a single pending command and `Start` does nothing to a running clip, so
+/// running clips are only rewound. Any other clip is started, resuming a paused one.
+fn rewind(controls: &mut AnimationControlSet<usize, Transform>, id: usize) {
+    let running = controls
+        .animations
+        .iter()
+        .any(|(i, control)| *i == id && matches!(control.state, ControlState::Running(_)));
+    if running {
+        controls.set_input(id, 0.0);
+    } else {
+        controls.start(id);
+    }
+}
+
+/// Applies `command` to an entity's animations and returns the clip now selected.
+///
+/// `current` is the clip selected before the command, if any. Speed changes are stored in
+/// `step` so that `AnimationStepSystem` keeps applying them. When switching clips with a
+/// `crossfade`, the outgoing clip keeps playing until `CrossFadeSystem` fades it out.
+pub fn apply_playback(
+    command: PlaybackCommand,
+    current: Option<usize>,
+    animations: &AnimationSet<usize, Transform>,
+    controls: &mut AnimationControlSet<usize, Transform>,
+    step: &mut AnimationStep,
+    mut crossfade: Option<&mut CrossFade>,
+) -> Option<usize> {
+    let count = animations.animations.len();
+    if count == 0 {
+        return None;
+    }
+    let speed = step.speed;
+    // Clip ids are glTF animation indices, so they are contiguous from zero.
+    let mut switch_to = |id: usize, controls: &mut AnimationControlSet<usize, Transform>| {
+        if let Some(current) = current.filter(|&current| current != id) {
+            let stop = match crossfade.as_mut() {
+                Some(crossfade) => crossfade.start(current, id),
+                None => Some(current),
+            };
+            if let Some(stop) = stop {
+                controls.abort(stop);
+            }
+        }
+        if let Some(animation) = animations.animations.get(&id) {
+            if controls.has_animation(id) {
+                rewind(controls, id);
+            } else {
+                controls.add_animation(
+                    id,
+                    animation,
+                    EndControl::Loop(None),
+                    speed,
+                    AnimationCommand::Start,
+                );
+            }
+        }
+        Some(id)
+    };
+
+    match (command, current) {
+        (PlaybackCommand::Play(id), _) if id < count => switch_to(id, controls),
+        (PlaybackCommand::Play(_), current) => current,
+        (PlaybackCommand::PlayPause, None) | (PlaybackCommand::Restart, None) => {
+            switch_to(0, controls)
+        }
+        (PlaybackCommand::PlayPause, Some(id)) => {
+            controls.toggle(id);
+            Some(id)
+        }
+        (PlaybackCommand::Restart, Some(id)) => switch_to(id, controls),
+        (PlaybackCommand::Next, current) => {
+            switch_to(current.map(|id| (id + 1) % count).unwrap_or(0), controls)
+        }
+        (PlaybackCommand::Previous, current) => switch_to(
+            current
+                .map(|id| (id + count - 1) % count)
+                .unwrap_or(count - 1),
+            controls,
+        ),
+        (PlaybackCommand::Faster, current) | (PlaybackCommand::Slower, current) => {
+            let faster = command == PlaybackCommand::Faster;
+            let index = SPEEDS
+                .iter()
+                .position(|&speed| speed >= step.speed)
+                .unwrap_or(SPEEDS.len() - 1);
+            let index = if faster {
+                (index + 1).min(SPEEDS.len() - 1)
+            } else {
+                index.saturating_sub(1)
+            };
+            step.speed = SPEEDS[index];
+            if let Some(id) = current {
+                controls.set_rate(id, step.speed);
+            }
+            current
+        }
+        (PlaybackCommand::StepForward, Some(id)) => {
+            controls.step(id, StepDirection::Forward);
+            Some(id)
+        }
+        (PlaybackCommand::StepBackward, Some(id)) => {
+            controls.step(id, StepDirection::Backward);
+            Some(id)
+        }
+        (PlaybackCommand::StepForward, None) | (PlaybackCommand::StepBackward, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::{
+        animation::Animation,
+        assets::{AssetStorage, Handle, Loader},
+    };
+    use rayon::ThreadPoolBuilder;
+
+    use std::{sync::Arc, time::Duration};
+
+    // Eight frames per second and a display running at twice that keep every value exact.
+    fn step(frames: u32, speed: f32) -> AnimationStep {
+        AnimationStep {
+            frames,
+            frame_rate: 8.0,
+            speed,
+            ..Default::default()
+        }
+    }
+
+    fn rates(step: &mut AnimationStep, count: usize) -> Vec<f32> {
+        (0..count).map(|_| step.rate(0.0625)).collect()
+    }
+
+    #[test]
+    fn ones_play_every_frame() {
+        assert_eq!(rates(&mut step(1, 1.0), 3), vec![1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn twos_hold_every_other_pose() {
+        // A step lasts two animation frames, i.e. four display frames.
+        assert_eq!(
+            rates(&mut step(2, 1.0), 8),
+            vec![0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn threes_hold_each_pose_for_three_frames() {
+        assert_eq!(
+            rates(&mut step(3, 1.0), 12),
+            vec![0.0, 0.0, 0.0, 0.0, 0.0, 6.0, 0.0, 0.0, 0.0, 0.0, 0.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn speed_scales_the_stepped_rate() {
+        assert_eq!(rates(&mut step(1, 0.5), 2), vec![0.5, 0.5]);
+        assert_eq!(rates(&mut step(2, 0.5), 4), vec![0.0, 0.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn stepping_keeps_the_average_rate() {
+        // Deltas that do not divide the step carry the remainder over to the next pose.
+        let mut step = step(2, 1.0);
+        let advanced: f32 = (0..100).map(|_| step.rate(0.1) * 0.1).sum();
+        assert!((advanced - 10.0).abs() <= 0.25);
+    }
+
+    fn clip() -> Handle<Animation<Transform>> {
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+        let storage = AssetStorage::<Animation<Transform>>::new();
+        Loader::new(".", pool).load_from_data(Animation { nodes: Vec::new() }, (), &storage)
+    }
+
+    /// Clip 0 of a single clip set, with its control already past its start command.
+    fn playing(
+        state: ControlState,
+    ) -> (
+        AnimationSet<usize, Transform>,
+        AnimationControlSet<usize, Transform>,
+    ) {
+        let clip = clip();
+        let mut animations = AnimationSet::new();
+        animations.insert(0, clip.clone());
+        let mut controls = AnimationControlSet::default();
+        controls.add_animation(
+            0,
+            &clip,
+            EndControl::Loop(None),
+            1.0,
+            AnimationCommand::Init,
+        );
+        controls.animations[0].1.state = state;
+        (animations, controls)
+    }
+
+    #[test]
+    fn restart_rewinds_a_running_clip() {
+        let (animations, mut controls) = playing(ControlState::Running(Duration::from_secs(1)));
+        let current = apply_playback(
+            PlaybackCommand::Restart,
+            Some(0),
+            &animations,
+            &mut controls,
+            &mut AnimationStep::default(),
+            None,
+        );
+        assert_eq!(current, Some(0));
+        let command = &controls.animations[0].1.command;
+        assert!(
+            matches!(command, AnimationCommand::SetInputValue(input) if *input == 0.0),
+            "{:?}",
+            command
+        );
+    }
+
+    #[test]
+    fn restart_starts_a_paused_clip() {
+        let (animations, mut controls) = playing(ControlState::Paused(Duration::from_secs(1)));
+        apply_playback(
+            PlaybackCommand::Restart,
+            Some(0),
+            &animations,
+            &mut controls,
+            &mut AnimationStep::default(),
+            None,
+        );
+        assert!(matches!(
+            controls.animations[0].1.command,
+            AnimationCommand::Start
+        ));
+    }
+
+    #[test]
+    fn disabled_cross_fade_stops_the_outgoing_clip() {
+        let mut crossfade = CrossFade {
+            duration: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(crossfade.start(0, 1), Some(0));
+        assert_eq!(crossfade.active, None);
+    }
+
+    #[test]
+    fn cross_fade_keeps_the_outgoing_clip_playing() {
+        let mut crossfade = CrossFade::default();
+        assert_eq!(crossfade.start(0, 1), None);
+        assert_eq!(
+            crossfade.active,
+            Some(Fade {
+                from: 0,
+                to: 1,
+                elapsed: 0.0
+            })
+        );
+    }
+
+    #[test]
+    fn interrupted_cross_fade_stops_the_oldest_clip() {
+        let mut crossfade = CrossFade::default();
+        crossfade.start(0, 1);
+        assert_eq!(crossfade.start(1, 2), Some(0));
+        let fade = crossfade.active.unwrap();
+        assert_eq!((fade.from, fade.to), (1, 2));
+    }
+
+    #[test]
+    fn cross_fade_back_to_the_outgoing_clip_stops_nothing() {
+        let mut crossfade = CrossFade::default();
+        crossfade.start(0, 1);
+        crossfade.active.as_mut().unwrap().elapsed = 0.1;
+        assert_eq!(crossfade.start(1, 0), None);
+        assert_eq!(
+            crossfade.active,
+            Some(Fade {
+                from: 1,
+                to: 0,
+                elapsed: 0.0
+            })
+        );
+    }
+}
@@ -0,0 +1,470 @@
+use amethyst::{
+    assets::lazy_static,
+    core::ecs::{DispatcherBuilder, Read, SystemData, World},
+    renderer::{
+        bundle::{
+            ImageOptions, OutputColor, RenderOrder, RenderPlan, RenderPlugin, Target, TargetImage,
+            TargetPlanOutputs,
+        },
+        pipeline::{PipelineDescBuilder, PipelinesBuilder},
+        rendy::{
+            command::{QueueId, RenderPassEncoder},
+            factory::Factory,
+            graph::{
+                render::{PrepareResult, RenderGroup, RenderGroupDesc},
+                GraphContext, ImageAccess, NodeBuffer, NodeImage,
+            },
+            hal::{
+                self,
+                command::{ClearColor, ClearDepthStencil, ClearValue},
+                device::Device,
+                format::Format,
+                image::{Filter, Kind, SamplerInfo, ViewKind, WrapMode},
+                pso,
+                pso::ShaderStageFlags,
+            },
+            resource::{
+                DescriptorSet, DescriptorSetLayout, Escape, Handle, ImageView, ImageViewInfo,
+                Sampler,
+            },
+            shader::{Shader, SpirvShader},
+        },
+        types::Backend,
+        util, ChangeDetection,
+    },
+    window::ScreenDimensions,
+    Error,
+};
+use derivative::*;
+
+use crate::style::{PostEffect, PostEffects};
+
+lazy_static::lazy_static! {
+    // These uses the precompiled shaders.
+    // These can be obtained using glslc.exe in the vulkan sdk.
+    static ref VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../assets/shaders/fullscreen.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../assets/shaders/antialias.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+
+    // antialias.frag compiled with `glslc -DMULTISAMPLE`.
+    static ref FRAGMENT_MULTISAMPLE: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../assets/shaders/antialias_ms.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+}
+
+/// Offscreen target the cel-shaded scene and its strokes are drawn into, so it can be
+/// multisampled and anti-aliased before it reaches the window.
+pub const SCENE_TARGET: Target = Target::Custom("scene");
+
+/// Anti-aliasing of the scene target, set from `config/render.ron`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Antialiasing {
+    /// Samples per pixel of the scene target, fixed at startup.
+    pub msaa_samples: u8,
+    /// Run FXAA whatever the active style preset asks for.
+    pub fxaa: bool,
+}
+
+impl Default for Antialiasing {
+    fn default() -> Self {
+        Antialiasing {
+            msaa_samples: 1,
+            fxaa: false,
+        }
+    }
+}
+
+impl Antialiasing {
+    /// Short description for the HUD, e.g. "MSAA 4x + FXAA".
+    pub fn name(&self, post_effects: &PostEffects) -> String {
+        let fxaa = post_effects.0.contains(&PostEffect::Fxaa);
+        match (self.msaa_samples > 1, fxaa) {
+            (true, true) => format!("MSAA {}x + FXAA", self.msaa_samples),
+            (true, false) => format!("MSAA {}x", self.msaa_samples),
+            (false, true) => "FXAA".to_string(),
+            (false, false) => "off".to_string(),
+        }
+    }
+
+    /// Turns FXAA on or off for the current preset, and keeps that choice across presets.
+    pub fn toggle_fxaa(&mut self, post_effects: &mut PostEffects) {
+        self.fxaa = !post_effects.0.contains(&PostEffect::Fxaa);
+        post_effects.0.retain(|&effect| effect != PostEffect::Fxaa);
+        if self.fxaa {
+            post_effects.0.push(PostEffect::Fxaa);
+        }
+    }
+}
+
+/// Multisampling state for pipelines drawing into a target with `samples` samples per pixel.
+pub fn multisampling(samples: u8) -> Option<pso::Multisampling> {
+    if samples <= 1 {
+        return None;
+    }
+    Some(pso::Multisampling {
+        rasterization_samples: samples,
+        sample_shading: None,
+        sample_mask: !0,
+        alpha_coverage: false,
+        alpha_to_one: false,
+    })
+}
+
+/// Push constants of `antialias.frag`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct AntialiasArgs {
+    inverse_size: [f32; 2],
+    samples: i32,
+    fxaa: bool,
+}
+
+impl AntialiasArgs {
+    fn words(&self) -> [u32; 4] {
+        [
+            self.inverse_size[0].to_bits(),
+            self.inverse_size[1].to_bits(),
+            self.samples as u32,
+            self.fxaa as u32,
+        ]
+    }
+}
+
+/// Resolves `SCENE_TARGET` onto the window, applying FXAA when it is in `PostEffects`.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawAntialiasDesc {
+    msaa_samples: u8,
+}
+
+impl DrawAntialiasDesc {
+    /// Create instance of `DrawAntialiasDesc` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sample count of the scene target being resolved.
+    pub fn with_msaa_samples(mut self, samples: u8) -> Self {
+        self.msaa_samples = samples;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawAntialiasDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![ImageAccess {
+            access: hal::image::Access::SHADER_READ,
+            usage: hal::image::Usage::SAMPLED,
+            layout: hal::image::Layout::ShaderReadOnlyOptimal,
+            stages: pso::PipelineStage::FRAGMENT_SHADER,
+        }]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _world: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        let scene = &images[0];
+        let image = ctx
+            .get_image(scene.id)
+            .ok_or_else(|| failure::format_err!("scene target image is missing"))?;
+        let view = factory.create_image_view(
+            image.clone(),
+            ImageViewInfo {
+                view_kind: ViewKind::D2,
+                format: image.format(),
+                swizzle: hal::format::Swizzle::NO,
+                range: scene.range.clone(),
+            },
+        )?;
+        // Texels are fetched directly, the sampler is only there to satisfy the binding.
+        let sampler = factory.get_sampler(SamplerInfo::new(Filter::Nearest, WrapMode::Clamp))?;
+
+        let set_layout: Handle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(vec![pso::DescriptorSetLayoutBinding {
+                binding: 0,
+                ty: pso::DescriptorType::CombinedImageSampler,
+                count: 1,
+                stage_flags: ShaderStageFlags::FRAGMENT,
+                immutable_samplers: false,
+            }])?
+            .into();
+        let set = factory.create_descriptor_set(set_layout.clone())?;
+        unsafe {
+            factory.device().write_descriptor_sets(vec![pso::DescriptorSetWrite {
+                set: set.raw(),
+                binding: 0,
+                array_offset: 0,
+                descriptors: Some(pso::Descriptor::CombinedImageSampler(
+                    view.raw(),
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                    sampler.raw(),
+                )),
+            }]);
+        }
+
+        let (pipeline, pipeline_layout) = build_antialias_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.msaa_samples > 1,
+            vec![set_layout.raw()],
+        )?;
+
+        Ok(Box::new(DrawAntialias::<B> {
+            pipeline,
+            pipeline_layout,
+            _set_layout: set_layout,
+            set,
+            _view: view,
+            _sampler: sampler,
+            args: AntialiasArgs {
+                inverse_size: [
+                    1.0 / framebuffer_width as f32,
+                    1.0 / framebuffer_height as f32,
+                ],
+                samples: i32::from(self.msaa_samples.max(1)),
+                fxaa: false,
+            },
+            change: Default::default(),
+        }))
+    }
+}
+
+/// Draws a full-screen triangle sampling the scene target.
+#[derive(Debug)]
+pub struct DrawAntialias<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    // Kept alive for as long as the descriptor set refers to them
+    _set_layout: Handle<DescriptorSetLayout<B>>,
+    set: Escape<DescriptorSet<B>>,
+    _view: Escape<ImageView<B>>,
+    _sampler: Handle<Sampler<B>>,
+    args: AntialiasArgs,
+    change: ChangeDetection,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawAntialias<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        let post_effects = <Read<'_, PostEffects>>::fetch(world);
+        let fxaa = post_effects.0.contains(&PostEffect::Fxaa);
+        let changed = self.args.fxaa != fxaa;
+        self.args.fxaa = fxaa;
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                &self.pipeline_layout,
+                0,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+            encoder.push_constants(
+                &self.pipeline_layout,
+                ShaderStageFlags::FRAGMENT,
+                0,
+                &self.args.words(),
+            );
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_antialias_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    multisampled: bool,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory.device().create_pipeline_layout(
+            layouts,
+            vec![(
+                ShaderStageFlags::FRAGMENT,
+                0..std::mem::size_of::<[u32; 4]>() as u32,
+            )],
+        )
+    }?;
+
+    let fragment = if multisampled {
+        &*FRAGMENT_MULTISAMPLE
+    } else {
+        &*FRAGMENT
+    };
+    let shader_vertex = unsafe { VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { fragment.module(factory).unwrap() };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleList))
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                // The scene is cleared to transparent, so the skybox behind it shows through.
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::PREMULTIPLIED_ALPHA),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
+
+/// A `RenderPlugin` that defines `SCENE_TARGET` at the window size and composites it onto
+/// the window after the scene's outlines and strokes are drawn.
+///
+/// Add it before `RenderDebugLines` so the composite is drawn first.
+#[derive(Default, Debug)]
+pub struct RenderAntialiasing {
+    msaa_samples: u8,
+    dimensions: Option<ScreenDimensions>,
+    dirty: bool,
+}
+
+impl RenderAntialiasing {
+    /// Set the samples per pixel of the scene target, 1 to disable MSAA.
+    pub fn with_msaa_samples(mut self, samples: u8) -> Self {
+        self.msaa_samples = samples;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderAntialiasing {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.entry::<PostEffects>().or_insert_with(Default::default);
+        let samples = self.msaa_samples.max(1);
+        world
+            .entry::<Antialiasing>()
+            .or_insert_with(Default::default)
+            .msaa_samples = samples;
+        Ok(())
+    }
+
+    fn should_rebuild(&mut self, world: &World) -> bool {
+        let dimensions = world.try_fetch::<ScreenDimensions>();
+        if self.dimensions.as_ref() != dimensions.as_deref() {
+            self.dirty = true;
+            self.dimensions = dimensions.map(|d| (*d).clone());
+            return false;
+        }
+        self.dirty
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        self.dirty = false;
+        let (width, height) = self
+            .dimensions
+            .as_ref()
+            .map_or((1, 1), |d| (d.width() as u32, d.height() as u32));
+        let samples = self.msaa_samples.max(1);
+        let kind = Kind::D2(width.max(1), height.max(1), 1, samples);
+
+        plan.define_pass(
+            SCENE_TARGET,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba8Srgb,
+                    clear: Some(ClearValue::Color(ClearColor::Sfloat([0.0; 4]))),
+                })],
+                // Cleared to the far plane of the `Less` depth test the scene passes use.
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(1.0, 0))),
+                }),
+            },
+        )?;
+
+        plan.extend_target(Target::Main, move |ctx| {
+            let scene = ctx.get_image(TargetImage::Color(SCENE_TARGET, 0))?;
+            // After the skybox but before the debug lines, which would otherwise be painted
+            // over by the scene as the window has no depth to test them against.
+            ctx.add(
+                RenderOrder::BeforeTransparent,
+                DrawAntialiasDesc::new()
+                    .with_msaa_samples(samples)
+                    .builder()
+                    .with_image(scene),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use amethyst::core::{
+    ecs::{Read, System, Write},
+    math::{Point3, Vector3},
+    Time,
+};
+use serde::{Deserialize, Serialize};
+
+/// Hand-drawn "line boil": line work that is re-traced with slightly different noise
+/// every few frames instead of staying perfectly still.
+///
+/// Off by default; the B key toggles it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LineBoil {
+    pub enabled: bool,
+    /// Number of frames each tracing of the lines is held for, e.g. 3 to boil on threes.
+    pub rate: u32,
+    /// Maximum displacement of strokes in world units.
+    pub stroke_amplitude: f32,
+    /// How much the outline's N·V threshold wobbles in the cel shader.
+    pub outline_amplitude: f32,
+    /// Spatial frequency of the wobble; higher values give shakier lines.
+    pub frequency: f32,
+    /// Maximum texture coordinate offset applied to the brush texture of strokes.
+    pub uv_jitter: f32,
+    /// Advanced by `LineBoilSystem` every `rate` frames.
+    #[serde(skip)]
+    pub seed: u32,
+}
+
+impl Default for LineBoil {
+    fn default() -> Self {
+        LineBoil {
+            enabled: false,
+            rate: 3,
+            stroke_amplitude: 0.02,
+            outline_amplitude: 0.05,
+            frequency: 1.5,
+            uv_jitter: 0.25,
+            seed: 0,
+        }
+    }
+}
+
+impl LineBoil {
+    /// Texture coordinate offset for the current tracing.
+    pub fn uv_offset(&self) -> [f32; 2] {
+        if !self.enabled {
+            return [0.0, 0.0];
+        }
+        [
+            hash(self.seed, 0) * self.uv_jitter,
+            hash(self.seed, 1) * self.uv_jitter,
+        ]
+    }
+
+    /// Smooth displacement of a world-space point for the current tracing.
+    pub fn displacement(&self, point: &Point3<f32>) -> Vector3<f32> {
+        if !self.enabled || self.stroke_amplitude == 0.0 {
+            return Vector3::zeros();
+        }
+        let p = point.coords * self.frequency;
+        Vector3::new(
+            value_noise3(self.seed.wrapping_mul(3), &p),
+            value_noise3(self.seed.wrapping_mul(3) + 1, &p),
+            value_noise3(self.seed.wrapping_mul(3) + 2, &p),
+        ) * self.stroke_amplitude
+    }
+
+    /// Seed to mix into per-vertex jitter so that it changes with each tracing.
+    pub fn jitter_seed(&self) -> u32 {
+        if self.enabled {
+            self.seed.wrapping_mul(0x68E3_1DA4)
+        } else {
+            0
+        }
+    }
+}
+
+/// Re-seeds `LineBoil` every `rate` frames.
+#[derive(Debug, Default)]
+pub struct LineBoilSystem;
+
+impl<'a> System<'a> for LineBoilSystem {
+    type SystemData = (Read<'a, Time>, Write<'a, LineBoil>);
+
+    fn run(&mut self, (time, mut boil): Self::SystemData) {
+        if boil.enabled && boil.rate > 0 && time.frame_number() % u64::from(boil.rate) == 0 {
+            boil.seed = boil.seed.wrapping_add(1);
+        }
+    }
+}
+
+/// Cheap integer hash mapped to `[-1, 1]`, used for deterministic jitter.
+pub fn hash(seed: u32, n: u32) -> f32 {
+    let mut x = seed.wrapping_mul(0x9E37_79B9) ^ n.wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^= x >> 16;
+    (x as f32 / std::u32::MAX as f32) * 2.0 - 1.0
+}
+
+/// Smooth 1D value noise in `[-1, 1]`.
+pub fn value_noise(seed: u32, x: f32) -> f32 {
+    let cell = x.floor();
+    let t = smooth(x - cell);
+    let cell = cell as i32 as u32;
+    let (a, b) = (hash(seed, cell), hash(seed, cell.wrapping_add(1)));
+    a + (b - a) * t
+}
+
+/// Smooth 3D value noise in `[-1, 1]`.
+pub fn value_noise3(seed: u32, p: &Vector3<f32>) -> f32 {
+    let cell = p.map(f32::floor);
+    let t = (p - cell).map(smooth);
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        let x = (cell.x as i32 + dx) as u32;
+        let y = (cell.y as i32 + dy) as u32;
+        let z = (cell.z as i32 + dz) as u32;
+        hash(seed, x ^ y.wrapping_mul(0x0001_9F3B) ^ z.wrapping_mul(0x0035_6C91))
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    lerp(
+        lerp(
+            lerp(corner(0, 0, 0), corner(1, 0, 0), t.x),
+            lerp(corner(0, 1, 0), corner(1, 1, 0), t.x),
+            t.y,
+        ),
+        lerp(
+            lerp(corner(0, 0, 1), corner(1, 0, 1), t.x),
+            lerp(corner(0, 1, 1), corner(1, 1, 1), t.x),
+            t.y,
+        ),
+        t.z,
+    )
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,137 @@
+use crate::options::ModelFormat;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Models found in the assets directory, cycled through at runtime.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelBrowser {
+    /// Model paths relative to the assets directory, with `/` separators.
+    pub models: Vec<String>,
+    /// Index of the model currently shown, if it came from the browser.
+    pub current: Option<usize>,
+}
+
+impl ModelBrowser {
+    /// Recursively collects every model the model prefab can load under `assets_dir`.
+    pub fn scan(assets_dir: &Path) -> io::Result<Self> {
+        let mut models = Vec::new();
+        let mut dirs = vec![PathBuf::new()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(assets_dir.join(&dir))? {
+                let entry = entry?;
+                let path = dir.join(entry.file_name());
+                if entry.file_type()?.is_dir() {
+                    dirs.push(path);
+                } else if is_loadable(&path) {
+                    let components = path
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>();
+                    models.push(components.join("/"));
+                }
+            }
+        }
+        models.sort();
+        Ok(ModelBrowser {
+            models,
+            current: None,
+        })
+    }
+
+    /// Selects `path` if it is one of the scanned models.
+    pub fn select(&mut self, path: &str) {
+        self.current = self.models.iter().position(|model| model == path);
+    }
+
+    /// Moves to the next model, wrapping around, and returns its path.
+    pub fn next_model(&mut self) -> Option<&str> {
+        self.step(1)
+    }
+
+    /// Moves to the previous model, wrapping around, and returns its path.
+    pub fn previous_model(&mut self) -> Option<&str> {
+        self.step(self.models.len().saturating_sub(1))
+    }
+
+    fn step(&mut self, by: usize) -> Option<&str> {
+        if self.models.is_empty() {
+            return None;
+        }
+        let next = match self.current {
+            Some(current) => (current + by) % self.models.len(),
+            None => 0,
+        };
+        self.current = Some(next);
+        self.models.get(next).map(String::as_str)
+    }
+
+    /// At most `rows` lines of the model list, scrolled to keep the current model in view.
+    pub fn listing(&self, rows: usize) -> Vec<String> {
+        let current = self.current.unwrap_or(0);
+        let first = current
+            .saturating_sub(rows / 2)
+            .min(self.models.len().saturating_sub(rows));
+        self.models
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .map(|(index, model)| {
+                let marker = if self.current == Some(index) { '>' } else { ' ' };
+                format!("{} {}: {}", marker, index, model)
+            })
+            .collect()
+    }
+}
+
+fn is_loadable(path: &Path) -> bool {
+    ModelFormat::from_path(path).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browser(count: usize, current: Option<usize>) -> ModelBrowser {
+        ModelBrowser {
+            models: (0..count).map(|i| format!("model_{}.gltf", i)).collect(),
+            current,
+        }
+    }
+
+    #[test]
+    fn steps_wrap_around() {
+        let mut browser = browser(3, None);
+        assert_eq!(browser.next_model(), Some("model_0.gltf"));
+        assert_eq!(browser.previous_model(), Some("model_2.gltf"));
+        assert_eq!(browser.next_model(), Some("model_0.gltf"));
+        assert_eq!(ModelBrowser::default().next_model(), None);
+    }
+
+    #[test]
+    fn listing_marks_the_current_model() {
+        assert_eq!(
+            browser(3, Some(1)).listing(5),
+            vec![
+                "  0: model_0.gltf",
+                "> 1: model_1.gltf",
+                "  2: model_2.gltf"
+            ]
+        );
+    }
+
+    #[test]
+    fn listing_scrolls_to_the_current_model() {
+        let first = |listing: Vec<String>| listing[0].clone();
+        assert_eq!(browser(20, None).listing(4).len(), 4);
+        assert_eq!(first(browser(20, None).listing(4)), "  0: model_0.gltf");
+        assert_eq!(first(browser(20, Some(10)).listing(4)), "  8: model_8.gltf");
+        assert_eq!(
+            first(browser(20, Some(19)).listing(4)),
+            "  16: model_16.gltf"
+        );
+    }
+}
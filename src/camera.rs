@@ -0,0 +1,658 @@
+use amethyst::{
+    core::{
+        ecs::{Component, DenseVecStorage, Join, Read, ReadStorage, System, WriteStorage},
+        math::{Matrix4, Point3, Quaternion, UnitQuaternion, Vector3},
+        Time, Transform,
+    },
+    input::{BindingTypes, InputHandler},
+    renderer::{
+        camera::{Orthographic, Perspective, Projection},
+        Camera,
+    },
+    winit::MouseButton,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lines::LineMesh;
+
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// Direction the camera looks at a framed model from, before normalization.
+const FRAMING_DIRECTION: [f32; 3] = [0.0, 0.35, 1.0];
+/// Extra room left around a framed model, as a fraction of its radius.
+const FRAMING_MARGIN: f32 = 1.15;
+
+/// Axis-aligned bounding box in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Bounds {
+    pub fn from_points<'a, I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a Point3<f32>>,
+    {
+        let mut points = points.into_iter();
+        let first = *points.next()?;
+        Some(points.fold(
+            Bounds {
+                min: first,
+                max: first,
+            },
+            |bounds, point| bounds.including(point),
+        ))
+    }
+
+    /// Bounds of `mesh` after transforming it by `matrix`.
+    pub fn of_mesh(mesh: &LineMesh, matrix: &Matrix4<f32>) -> Option<Self> {
+        let points = mesh
+            .positions()
+            .iter()
+            .map(|p| matrix.transform_point(p))
+            .collect::<Vec<_>>();
+        Self::from_points(&points)
+    }
+
+    pub fn including(&self, point: &Point3<f32>) -> Self {
+        Bounds {
+            min: self.min.inf(point),
+            max: self.max.sup(point),
+        }
+    }
+
+    pub fn union(&self, other: &Bounds) -> Self {
+        self.including(&other.min).including(&other.max)
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        Point3::from((self.min.coords + self.max.coords) * 0.5)
+    }
+
+    /// Radius of the sphere through the box's corners.
+    pub fn radius(&self) -> f32 {
+        (self.max - self.min).norm() * 0.5
+    }
+}
+
+/// Moves `transform` back from `bounds` far enough for `camera` to see all of it, and fits
+/// the camera's clipping planes around it.
+pub fn frame_bounds(camera: &mut Camera, transform: &mut Transform, bounds: &Bounds) {
+    let center = bounds.center();
+    let radius = bounds.radius().max(1.0e-3) * FRAMING_MARGIN;
+
+    let distance = match camera.projection_mut() {
+        Projection::Perspective(perspective) => {
+            // The narrower of the two fields of view decides how far back the camera goes.
+            let half_fovy = perspective.fovy() * 0.5;
+            let half_fovx = (half_fovy.tan() * perspective.aspect()).atan();
+            let distance = radius / half_fovy.min(half_fovx).sin();
+            perspective.set_near((distance - radius).max(radius * 1.0e-3));
+            // Leave room to fly back out with the fly camera.
+            perspective.set_far((distance + radius) * 4.0);
+            distance
+        }
+        Projection::Orthographic(orthographic) => {
+            let aspect = ortho_aspect(orthographic);
+            set_ortho_extent(orthographic, radius * (1.0 / aspect).max(1.0), aspect);
+            let distance = radius * 2.0;
+            orthographic.set_near((distance - radius).max(radius * 1.0e-3));
+            orthographic.set_far((distance + radius) * 4.0);
+            distance
+        }
+    };
+
+    let direction = Vector3::from(FRAMING_DIRECTION).normalize();
+    let position = center + direction * distance;
+    transform.set_translation(position.coords);
+    transform.face_towards(center.coords, Vector3::y());
+}
+
+/// Marks a camera switched to an orthographic projection, keeping the perspective settings to
+/// switch back to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrthographicView {
+    /// Vertical field of view of the perspective projection, in radians.
+    pub fovy: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Component for OrthographicView {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl OrthographicView {
+    /// Half the height of the orthographic view that shows as much as the perspective view
+    /// does at `distance`.
+    pub fn half_height(&self, distance: f32) -> f32 {
+        distance * (self.fovy * 0.5).tan()
+    }
+
+    /// Distance at which the perspective view shows `half_height` above the view axis.
+    pub fn distance(&self, half_height: f32) -> f32 {
+        half_height / (self.fovy * 0.5).tan()
+    }
+}
+
+fn ortho_aspect(orthographic: &Orthographic) -> f32 {
+    let height = orthographic.top() - orthographic.bottom();
+    if height.abs() > std::f32::EPSILON {
+        (orthographic.right() - orthographic.left()) / height
+    } else {
+        1.0
+    }
+}
+
+fn set_ortho_extent(orthographic: &mut Orthographic, half_height: f32, aspect: f32) {
+    orthographic.set_bottom_and_top(-half_height, half_height);
+    orthographic.set_left_and_right(-half_height * aspect, half_height * aspect);
+}
+
+/// Switches `camera` between a perspective and an orthographic projection, framing the same
+/// extent at `focus`, the point the viewer is looking at.
+///
+/// Going orthographic keeps the camera in place and sizes the view to what the perspective
+/// view showed at the focus distance; going back moves the camera along its view axis to the
+/// distance that shows the orthographic extent again. Returns the new distance to `focus`.
+pub fn toggle_projection(
+    camera: &mut Camera,
+    transform: &mut Transform,
+    view: &mut Option<OrthographicView>,
+    focus: &Point3<f32>,
+) -> f32 {
+    let offset = transform.translation() - focus.coords;
+    let distance = offset.norm().max(1.0e-3);
+    match (camera.projection().clone(), view.take()) {
+        (Projection::Perspective(perspective), _) => {
+            let saved = OrthographicView {
+                fovy: perspective.fovy(),
+                near: perspective.near(),
+                far: perspective.far(),
+            };
+            let half_height = saved.half_height(distance);
+            let aspect = perspective.aspect();
+            // The camera stays put, so keep the perspective depth range.
+            *camera.projection_mut() = Projection::Orthographic(Orthographic::new(
+                -half_height * aspect,
+                half_height * aspect,
+                -half_height,
+                half_height,
+                saved.near.min(distance * 0.5),
+                saved.far,
+            ));
+            *view = Some(saved);
+            distance
+        }
+        (Projection::Orthographic(orthographic), saved) => {
+            let aspect = ortho_aspect(&orthographic);
+            let half_height = (orthographic.top() - orthographic.bottom()) * 0.5;
+            let saved = saved.unwrap_or(OrthographicView {
+                fovy: std::f32::consts::FRAC_PI_3,
+                near: 0.1,
+                far: 2000.0,
+            });
+            let new_distance = saved.distance(half_height);
+            *camera.projection_mut() = Projection::Perspective(Perspective::new(
+                aspect,
+                saved.fovy,
+                saved.near,
+                saved.far.max(new_distance * 2.0),
+            ));
+            transform.set_translation(focus.coords + offset / distance * new_distance);
+            new_distance
+        }
+    }
+}
+
+/// Axonometric view angles for technical illustration, used with an orthographic projection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewPreset {
+    /// All three axes foreshortened equally.
+    Isometric,
+    /// The 2:1 dimetric view of pixel art and games, with lines along the ground axes at a
+    /// slope of one half.
+    Dimetric,
+}
+
+impl ViewPreset {
+    /// Yaw and pitch, in radians, of the camera around its target.
+    pub fn angles(self) -> (f32, f32) {
+        let yaw = std::f32::consts::FRAC_PI_4;
+        match self {
+            // Looking down the diagonal of a cube.
+            ViewPreset::Isometric => (yaw, (1.0 / 2.0f32.sqrt()).atan()),
+            ViewPreset::Dimetric => (yaw, 0.5f32.asin()),
+        }
+    }
+
+    /// Places `transform` at `distance` from `target`, looking at it from the preset's angles.
+    pub fn apply(self, transform: &mut Transform, target: &Point3<f32>, distance: f32) {
+        let (yaw, pitch) = self.angles();
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        let direction = Vector3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw);
+        transform.set_translation(target.coords + direction * distance);
+        transform.face_towards(target.coords, Vector3::y());
+    }
+}
+
+/// Orbits the camera around a target point: drag with the left mouse button to rotate, scroll
+/// to zoom and drag with the middle button to pan, or let it spin on its own as a turntable.
+///
+/// Meant to be swapped with `FlyControlTag` on the same camera entity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrbitCamera {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    /// Rotation around the target's vertical axis, in radians.
+    pub yaw: f32,
+    /// Elevation above the target, in radians.
+    pub pitch: f32,
+    /// Radians of rotation per pixel dragged.
+    pub rotate_sensitivity: f32,
+    /// Fraction of the distance zoomed per scroll step.
+    pub zoom_speed: f32,
+    /// Fraction of the distance panned per pixel dragged.
+    pub pan_speed: f32,
+    /// Spin automatically around the target.
+    pub turntable: bool,
+    /// Turntable speed in radians per second.
+    pub turntable_speed: f32,
+    last_mouse: Option<(f32, f32)>,
+}
+
+impl Component for OrbitCamera {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl OrbitCamera {
+    /// Orbit around `target` from wherever `transform` currently is.
+    pub fn looking_at(transform: &Transform, target: Point3<f32>) -> Self {
+        let offset = transform.translation() - target.coords;
+        let distance = offset.norm().max(1.0e-3);
+        OrbitCamera {
+            target,
+            distance,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).asin(),
+            rotate_sensitivity: 0.01,
+            zoom_speed: 0.1,
+            pan_speed: 0.002,
+            turntable: false,
+            turntable_speed: 0.5,
+            last_mouse: None,
+        }
+    }
+
+    /// Camera position for the current yaw, pitch and distance.
+    pub fn position(&self) -> Point3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        self.target
+            + Vector3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw) * self.distance
+    }
+}
+
+/// Drives cameras with an `OrbitCamera` from the mouse.
+#[derive(Debug)]
+pub struct OrbitCameraSystem<T: BindingTypes> {
+    marker: PhantomData<T>,
+}
+
+impl<T: BindingTypes> Default for OrbitCameraSystem<T> {
+    fn default() -> Self {
+        OrbitCameraSystem {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: BindingTypes> System<'a> for OrbitCameraSystem<T> {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, InputHandler<T>>,
+        ReadStorage<'a, OrthographicView>,
+        WriteStorage<'a, OrbitCamera>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (time, input, ortho_views, mut orbits, mut cameras, mut transforms): Self::SystemData,
+    ) {
+        let rotating = input.mouse_button_is_down(MouseButton::Left);
+        let panning = input.mouse_button_is_down(MouseButton::Middle);
+        let scroll = input.mouse_wheel_value(false);
+
+        for (orbit, transform, camera, ortho_view) in (
+            &mut orbits,
+            &mut transforms,
+            (&mut cameras).maybe(),
+            ortho_views.maybe(),
+        )
+            .join()
+        {
+            let mouse = input.mouse_position();
+            let (dx, dy) = match (orbit.last_mouse, mouse) {
+                (Some((x0, y0)), Some((x, y))) if rotating || panning => (x - x0, y - y0),
+                _ => (0.0, 0.0),
+            };
+            orbit.last_mouse = mouse;
+
+            if rotating {
+                orbit.yaw -= dx * orbit.rotate_sensitivity;
+                orbit.pitch = (orbit.pitch + dy * orbit.rotate_sensitivity).max(-1.5).min(1.5);
+            }
+            if panning {
+                let right = transform.rotation() * Vector3::x();
+                let up = transform.rotation() * Vector3::y();
+                orbit.target += (up * dy - right * dx) * orbit.distance * orbit.pan_speed;
+            }
+            if scroll != 0.0 {
+                orbit.distance =
+                    (orbit.distance * (1.0 - scroll * orbit.zoom_speed).max(0.1)).max(1.0e-3);
+                // Moving an orthographic camera closer changes nothing, so zoom its extent.
+                if let (Some(view), Some(camera)) = (ortho_view, camera) {
+                    if let Projection::Orthographic(orthographic) = camera.projection_mut() {
+                        let aspect = ortho_aspect(orthographic);
+                        set_ortho_extent(orthographic, view.half_height(orbit.distance), aspect);
+                    }
+                }
+            }
+            if orbit.turntable {
+                orbit.yaw += orbit.turntable_speed * time.delta_seconds();
+            }
+
+            transform.set_translation(orbit.position().coords);
+            transform.face_towards(orbit.target.coords, Vector3::y());
+        }
+    }
+}
+
+/// A saved camera placement.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CameraBookmark {
+    pub translation: [f32; 3],
+    /// Rotation quaternion as `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+    /// Vertical field of view in radians, for perspective cameras.
+    pub fovy: Option<f32>,
+}
+
+impl CameraBookmark {
+    pub fn capture(camera: &Camera, transform: &Transform) -> Self {
+        let t = transform.translation();
+        let r = transform.rotation().quaternion().coords;
+        CameraBookmark {
+            translation: [t.x, t.y, t.z],
+            rotation: [r.x, r.y, r.z, r.w],
+            fovy: match camera.projection() {
+                Projection::Perspective(perspective) => Some(perspective.fovy()),
+                Projection::Orthographic(_) => None,
+            },
+        }
+    }
+
+    pub fn apply(&self, camera: &mut Camera, transform: &mut Transform) {
+        let [x, y, z, w] = self.rotation;
+        transform.set_translation(Vector3::from(self.translation));
+        transform.set_rotation(UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)));
+        if let (Some(fovy), Projection::Perspective(perspective)) =
+            (self.fovy, camera.projection_mut())
+        {
+            perspective.set_fovy(fovy);
+        }
+    }
+}
+
+/// Camera bookmarks by slot number, stored as RON with `amethyst::config::Config`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraBookmarks {
+    pub slots: BTreeMap<u8, CameraBookmark>,
+}
+
+/// One point of a `CameraPath`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CameraKeyframe {
+    /// Seconds from the start of the path.
+    pub time: f32,
+    pub position: [f32; 3],
+    /// Point the camera looks at.
+    pub target: [f32; 3],
+    /// Vertical field of view in radians; unchanged when omitted.
+    #[serde(default)]
+    pub fovy: Option<f32>,
+}
+
+/// Keyframed camera move, interpolated with Catmull-Rom splines through the keyframes.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraPath {
+    /// Keyframes sorted by time.
+    pub keyframes: Vec<CameraKeyframe>,
+    /// Start over at the end instead of holding the last keyframe.
+    pub looping: bool,
+    /// Advance by exactly `1 / frame_rate` seconds per frame instead of by the frame time,
+    /// so renders of the path are repeatable.
+    pub frame_rate: Option<f32>,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Camera position, look-at target and field of view at `time`.
+    pub fn sample(&self, time: f32) -> Option<(Point3<f32>, Point3<f32>, Option<f32>)> {
+        let keys = &self.keyframes;
+        let last = keys.len().checked_sub(1)?;
+        let time = if self.looping && self.duration() > 0.0 {
+            time.rem_euclid(self.duration())
+        } else {
+            time
+        };
+        let next = keys.iter().position(|k| k.time > time).unwrap_or(last + 1);
+        if next == 0 || next > last {
+            let key = &keys[if next == 0 { 0 } else { last }];
+            return Some((key.position.into(), key.target.into(), key.fovy));
+        }
+
+        let (i0, i1, i2, i3) = (next.saturating_sub(2), next - 1, next, (next + 1).min(last));
+        let span = (keys[i2].time - keys[i1].time).max(1.0e-6);
+        let t = (time - keys[i1].time) / span;
+        let spline = |get: fn(&CameraKeyframe) -> [f32; 3]| {
+            let point = catmull_rom(
+                &get(&keys[i0]).into(),
+                &get(&keys[i1]).into(),
+                &get(&keys[i2]).into(),
+                &get(&keys[i3]).into(),
+                t,
+            );
+            Point3::from(point)
+        };
+        let fovy = match (keys[i1].fovy, keys[i2].fovy) {
+            (Some(a), Some(b)) => Some(a + (b - a) * t),
+            (a, b) => a.or(b),
+        };
+        Some((spline(|k| k.position), spline(|k| k.target), fovy))
+    }
+}
+
+fn catmull_rom(
+    p0: &Vector3<f32>,
+    p1: &Vector3<f32>,
+    p2: &Vector3<f32>,
+    p3: &Vector3<f32>,
+    t: f32,
+) -> Vector3<f32> {
+    let (t2, t3) = (t * t, t * t * t);
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// Plays a `CameraPath` on the camera it is attached to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    pub time: f32,
+}
+
+impl Component for CameraPathPlayer {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl CameraPathPlayer {
+    pub fn new(path: CameraPath) -> Self {
+        CameraPathPlayer { path, time: 0.0 }
+    }
+
+    /// Whether a non-looping path has reached its last keyframe.
+    pub fn finished(&self) -> bool {
+        !self.path.looping && self.time >= self.path.duration()
+    }
+
+    /// Places `camera` where the path is at the current time.
+    pub fn apply(&self, camera: &mut Camera, transform: &mut Transform) {
+        if let Some((position, target, fovy)) = self.path.sample(self.time) {
+            transform.set_translation(position.coords);
+            transform.face_towards(target.coords, Vector3::y());
+            if let (Some(fovy), Projection::Perspective(perspective)) =
+                (fovy, camera.projection_mut())
+            {
+                perspective.set_fovy(fovy);
+            }
+        }
+    }
+}
+
+/// Moves cameras along their `CameraPathPlayer`'s path, overriding the interactive controls.
+#[derive(Debug, Default)]
+pub struct CameraPathSystem;
+
+impl<'a> System<'a> for CameraPathSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        WriteStorage<'a, CameraPathPlayer>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (time, mut players, mut cameras, mut transforms): Self::SystemData) {
+        for (player, camera, transform) in (&mut players, &mut cameras, &mut transforms).join() {
+            player.apply(camera, transform);
+            if !player.finished() {
+                player.time += player
+                    .path
+                    .frame_rate
+                    .map_or(time.delta_seconds(), |rate| 1.0 / rate);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst::config::Config;
+
+    fn keyframe(time: f32, x: f32, fovy: Option<f32>) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            position: [x, 1.0, 10.0 - x * x],
+            target: [0.0, x * 0.5, 0.0],
+            fovy,
+        }
+    }
+
+    fn path(looping: bool) -> CameraPath {
+        CameraPath {
+            keyframes: vec![
+                keyframe(0.0, 0.0, Some(0.5)),
+                keyframe(1.0, 1.0, None),
+                keyframe(2.5, 3.0, Some(1.0)),
+                keyframe(4.0, -2.0, Some(0.8)),
+            ],
+            looping,
+            frame_rate: None,
+        }
+    }
+
+    #[test]
+    fn sample_hits_keyframes_exactly() {
+        let path = path(false);
+        for key in &path.keyframes {
+            let (position, target, fovy) = path.sample(key.time).unwrap();
+            assert_eq!(position, Point3::from(key.position));
+            assert_eq!(target, Point3::from(key.target));
+            if key.fovy.is_some() {
+                assert_eq!(fovy, key.fovy);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_interpolates_between_keyframes() {
+        let path = path(false);
+        let (position, _, fovy) = path.sample(3.25).unwrap();
+        assert!(position.x < 3.0 && position.x > -2.0);
+        assert!((fovy.unwrap() - 0.9).abs() < 1.0e-6);
+        // A missing field of view holds the neighbouring one.
+        assert_eq!(path.sample(0.5).unwrap().2, Some(0.5));
+    }
+
+    #[test]
+    fn sample_clamps_at_the_ends() {
+        let path = path(false);
+        let first = &path.keyframes[0];
+        let last = &path.keyframes[3];
+        assert_eq!(path.sample(-1.0).unwrap().0, Point3::from(first.position));
+        assert_eq!(path.sample(10.0).unwrap().0, Point3::from(last.position));
+        assert_eq!(path.sample(10.0).unwrap().2, last.fovy);
+        assert!(CameraPath::default().sample(0.0).is_none());
+    }
+
+    #[test]
+    fn looping_sample_wraps_around() {
+        let path = path(true);
+        assert_eq!(path.sample(5.0), path.sample(1.0));
+        assert_eq!(path.sample(-3.0), path.sample(1.0));
+    }
+
+    #[test]
+    fn bookmarks_round_trip_through_ron() {
+        let mut bookmarks = CameraBookmarks::default();
+        bookmarks.slots.insert(
+            1,
+            CameraBookmark {
+                translation: [0.1, -2.5, 30.0],
+                rotation: [0.0, 0.38268343, 0.0, 0.9238795],
+                fovy: Some(std::f32::consts::FRAC_PI_3),
+            },
+        );
+        bookmarks.slots.insert(
+            9,
+            CameraBookmark {
+                translation: [0.0, 0.0, 5.0],
+                rotation: [0.0, 0.0, 0.0, 1.0],
+                fovy: None,
+            },
+        );
+
+        let path = std::env::temp_dir().join(format!(
+            "npr_app_camera_bookmarks_{}.ron",
+            std::process::id()
+        ));
+        bookmarks.write(&path).unwrap();
+        let loaded = CameraBookmarks::load(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), bookmarks);
+    }
+}
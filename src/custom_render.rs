@@ -1,21 +1,33 @@
-use amethyst::{assets::lazy_static, renderer::{RenderBase3D, mtl::FullTextureSet, pass::{
-            Base3DPassDef, DrawBase3D, DrawBase3DDesc, DrawBase3DTransparent,
-            DrawBase3DTransparentDesc,
-        }, rendy::{hal::pso::ShaderStageFlags, mesh::{AsVertex, Normal, Position, Tangent, TexCoord, VertexFormat}, shader::{SpirvShader}}, skinning::JointCombined}};
+use amethyst::{assets::{AssetStorage, Handle, lazy_static}, core::{Transform, ecs::{DispatcherBuilder, Join, Read, ReadExpect, ReadStorage, SystemData, World, WorldExt}}, renderer::{Camera, Mesh, batch::{GroupIterator, OrderedOneLevelBatch, TwoLevelBatch}, bundle::{RenderOrder, RenderPlan, RenderPlugin, Target}, camera::{ActiveCamera, Projection}, mtl::{FullTextureSet, Material}, pass::Base3DPassDef, pipeline::{PipelineDescBuilder, PipelinesBuilder}, resources::Tint, rendy::{command::{QueueId, RenderPassEncoder}, factory::Factory, graph::{GraphContext, NodeBuffer, NodeImage, render::{PrepareResult, RenderGroup, RenderGroupDesc}}, hal::{self, device::Device, format::Format, pso, pso::ShaderStageFlags}, mesh::{AsVertex, Normal, Position, Tangent, TexCoord, VertexFormat}, shader::{Shader, SpirvShader}}, skinning::{JointCombined, JointTransforms}, submodules::{DynamicUniform, DynamicVertexBuffer, EnvironmentSub, MaterialId, MaterialSub, SkinningSub}, types::Backend, util, visibility::{Visibility, VisibilitySortingSystem}}, Error};
+use derivative::*;
+use glsl_layout::*;
+use smallvec::SmallVec;
 
-use std::path::PathBuf;
+use crate::{
+    antialiasing::multisampling,
+    boil::LineBoil,
+    debug_view::DebugView,
+    onion::OnionGhost,
+    squash::{Deformation, SquashStretchSystem},
+    style::{
+        BandSoftness, BandSoftnessSystem, CelStyle, InheritedBandSoftness, MAX_BANDS,
+        MAX_PALETTE,
+    },
+};
+
+use std::{marker::PhantomData, ops::Range};
 
 lazy_static::lazy_static! {
     // These uses the precompiled shaders.
     // These can be obtained using glslc.exe in the vulkan sdk.
     static ref VERTEX: SpirvShader = SpirvShader::from_bytes(
-        include_bytes!("../assets/shaders/pos_norm_tex.vert.spv"),
+        include_bytes!("../assets/shaders/npr.vert.spv"),
         ShaderStageFlags::VERTEX,
         "main",
     ).unwrap();
 
     static ref VERTEX_SKIN: SpirvShader = SpirvShader::from_bytes(
-        include_bytes!("../assets/shaders/pos_norm_tex_skin.vert.spv"),
+        include_bytes!("../assets/shaders/npr_skin.vert.spv"),
         ShaderStageFlags::VERTEX,
         "main",
     ).unwrap();
@@ -26,6 +38,12 @@ lazy_static::lazy_static! {
         ShaderStageFlags::FRAGMENT,
         "main",
     ).unwrap();
+
+    static ref ONION_FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../assets/shaders/onion.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
 }
 
 /// Implementation of `Base3DPassDef` for Physically-based (PBR) rendering pass.
@@ -62,14 +80,921 @@ impl Base3DPassDef for CustomPassDef {
     }
 }
 
-/// Describes a Physically-based (PBR) 3d Pass with lighting
-pub type DrawCustomDesc<B> = DrawBase3DDesc<B, CustomPassDef>;
-/// Draws a Physically-based (PBR) 3d Pass with lighting
-pub type DrawCustom<B> = DrawBase3D<B, CustomPassDef>;
-/// Describes a Physically-based (PBR) 3d Pass with lighting and transparency
-pub type DrawCustomTransparentDesc<B> = DrawBase3DTransparentDesc<B, CustomPassDef>;
-/// Draws a Physically-based (PBR) 3d Pass with lighting and transparency
-pub type DrawCustomTransparent<B> = DrawBase3DTransparent<B, CustomPassDef>;
+/// NprUniformArgs
+/// Stylization parameters shared by every mesh drawn with `CustomPassDef`.
+/// Uniform in shader:
+/// layout(std140, set = 3, binding = 0) uniform NprUniformArgs {
+///    float boil_seed;
+///    float boil_amplitude;
+///    float boil_frequency;
+///    vec3 ortho_view_direction;
+///    float orthographic;
+///    int debug_view;
+///    vec2 depth_range;
+///    vec4 band_thresholds;
+///    vec4 band_values;
+///    vec4 band_saturation;
+///    int band_count;
+///    float outline_threshold;
+///    float outline_opacity;
+///    int palette_size;
+///    vec4 outline_color;
+///    vec4 palette[8];
+///    float band_softness;
+/// };
+#[derive(Clone, Copy, Debug, AsStd140)]
+#[repr(C, align(4))]
+pub struct NprUniformArgs {
+    /// Changes every time the line boil re-seeds.
+    pub boil_seed: float,
+    /// How far the outline threshold wobbles.
+    pub boil_amplitude: float,
+    /// Spatial frequency of the outline wobble.
+    pub boil_frequency: float,
+    /// Direction towards an orthographic camera, used instead of the direction to the camera
+    /// position so outlines keep their width under both projections.
+    pub ortho_view_direction: vec3,
+    /// 1.0 when the active camera is orthographic.
+    pub orthographic: float,
+    /// `DebugView` index, 0 for regular shading.
+    pub debug_view: int,
+    /// Near and far plane distances of the active camera, for the depth debug view.
+    pub depth_range: vec2,
+    /// Upper brightness of each `CelStyle` band.
+    pub band_thresholds: vec4,
+    /// Brightness each band is drawn at.
+    pub band_values: vec4,
+    /// Saturation added within each band.
+    pub band_saturation: vec4,
+    pub band_count: int,
+    pub outline_threshold: float,
+    pub outline_opacity: float,
+    /// Number of `palette` colors in use, 0 to leave colors alone.
+    pub palette_size: int,
+    pub outline_color: vec4,
+    pub palette: [vec4; MAX_PALETTE],
+    /// Width in pixels of band edges, 0 for hard steps.
+    pub band_softness: float,
+}
+
+impl NprUniformArgs {
+    fn from_world(world: &World) -> Self {
+        let boil = world.read_resource::<LineBoil>();
+        let active = world.read_resource::<ActiveCamera>();
+        let cameras = world.read_storage::<Camera>();
+        let transforms = world.read_storage::<Transform>();
+        let camera = active
+            .entity
+            .and_then(|entity| Some((cameras.get(entity)?, transforms.get(entity)?)))
+            .or_else(|| (&cameras, &transforms).join().next());
+        let depth_range = match camera.map(|(camera, _)| camera.projection()) {
+            Some(Projection::Perspective(perspective)) => [perspective.near(), perspective.far()],
+            Some(Projection::Orthographic(orthographic)) => {
+                [orthographic.near(), orthographic.far()]
+            }
+            None => [0.1, 100.0],
+        };
+        let cel = world.read_resource::<CelStyle>();
+        let mut bands = [[1.0f32; MAX_BANDS]; 3];
+        for (i, band) in cel.bands.iter().take(MAX_BANDS).enumerate() {
+            bands[0][i] = band.threshold;
+            bands[1][i] = band.value;
+            bands[2][i] = band.saturation;
+        }
+        let mut palette = [[0.0f32; 4]; MAX_PALETTE];
+        for (entry, &[r, g, b]) in palette.iter_mut().zip(&cel.palette) {
+            *entry = [r, g, b, 1.0];
+        }
+        // Amethyst cameras look down their local -Z axis, so +Z points back at the viewer.
+        let ortho_view_direction = camera
+            .filter(|(camera, _)| matches!(camera.projection(), Projection::Orthographic(_)))
+            .map(|(_, transform)| transform.global_matrix().column(2).xyz().normalize());
+        NprUniformArgs {
+            boil_seed: (boil.seed % 1024) as f32,
+            boil_amplitude: if boil.enabled {
+                boil.outline_amplitude
+            } else {
+                0.0
+            },
+            boil_frequency: boil.frequency,
+            ortho_view_direction: ortho_view_direction
+                .map_or([0.0, 0.0, 1.0], Into::into)
+                .into(),
+            orthographic: if ortho_view_direction.is_some() { 1.0 } else { 0.0 },
+            debug_view: world.read_resource::<DebugView>().index(),
+            depth_range: depth_range.into(),
+            band_thresholds: bands[0].into(),
+            band_values: bands[1].into(),
+            band_saturation: bands[2].into(),
+            band_count: cel.bands.len().min(MAX_BANDS) as i32,
+            outline_threshold: cel.outline.threshold,
+            outline_opacity: cel.outline.opacity,
+            palette_size: cel.palette.len().min(MAX_PALETTE) as i32,
+            outline_color: cel.outline.color.into(),
+            palette: palette.map(Into::into),
+            band_softness: if cel.smooth_bands {
+                cel.band_softness.max(0.0)
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Per-instance vertex data of statically drawn meshes: Amethyst's `VertexArgs` extended with
+/// the entity's squash and stretch `Deformation` and `BandSoftness`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C, packed)]
+pub struct NprVertexArgs {
+    pub model: [[f32; 4]; 4],
+    pub tint: [f32; 4],
+    pub motion: [f32; 4],
+    pub smear: [f32; 4],
+    pub band_softness: f32,
+}
+
+impl NprVertexArgs {
+    pub fn from_object_data(
+        transform: &Transform,
+        tint: Option<&Tint>,
+        deformation: Option<&Deformation>,
+        softness: Option<&InheritedBandSoftness>,
+    ) -> Self {
+        let deformation = deformation.cloned().unwrap_or_default();
+        NprVertexArgs {
+            model: (*transform.global_matrix()).into(),
+            tint: tint.map_or([1.0; 4], |tint| {
+                let (r, g, b, a) = tint.0.into_components();
+                [r, g, b, a]
+            }),
+            motion: deformation.motion,
+            smear: deformation.smear,
+            band_softness: softness.map_or(1.0, |s| s.0),
+        }
+    }
+}
+
+impl AsVertex for NprVertexArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::Rgba32Sfloat, "model"),
+            (Format::Rgba32Sfloat, "model"),
+            (Format::Rgba32Sfloat, "model"),
+            (Format::Rgba32Sfloat, "model"),
+            (Format::Rgba32Sfloat, "tint"),
+            (Format::Rgba32Sfloat, "motion"),
+            (Format::Rgba32Sfloat, "smear"),
+            (Format::R32Sfloat, "band_softness"),
+        ))
+    }
+}
+
+/// Per-instance vertex data of skinned meshes, `NprVertexArgs` plus the offset of the
+/// instance's joints in the skinning buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C, packed)]
+pub struct NprSkinnedVertexArgs {
+    pub model: [[f32; 4]; 4],
+    pub tint: [f32; 4],
+    pub motion: [f32; 4],
+    pub smear: [f32; 4],
+    pub joints_offset: u32,
+    pub band_softness: f32,
+}
+
+impl NprSkinnedVertexArgs {
+    pub fn from_object_data(
+        transform: &Transform,
+        tint: Option<&Tint>,
+        deformation: Option<&Deformation>,
+        softness: Option<&InheritedBandSoftness>,
+        joints_offset: u32,
+    ) -> Self {
+        let NprVertexArgs {
+            model,
+            tint,
+            motion,
+            smear,
+            band_softness,
+        } = NprVertexArgs::from_object_data(transform, tint, deformation, softness);
+        NprSkinnedVertexArgs {
+            model,
+            tint,
+            motion,
+            smear,
+            joints_offset,
+            band_softness,
+        }
+    }
+}
+
+impl AsVertex for NprSkinnedVertexArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::Rgba32Sfloat, "model"),
+            (Format::Rgba32Sfloat, "model"),
+            (Format::Rgba32Sfloat, "model"),
+            (Format::Rgba32Sfloat, "model"),
+            (Format::Rgba32Sfloat, "tint"),
+            (Format::Rgba32Sfloat, "motion"),
+            (Format::Rgba32Sfloat, "smear"),
+            (Format::R32Uint, "joints_offset"),
+            (Format::R32Sfloat, "band_softness"),
+        ))
+    }
+}
+
+/// Describes the cel-shaded 3d pass, either for opaque or transparent meshes.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawCustomDesc<B: Backend> {
+    skinning: bool,
+    transparent: bool,
+    onion_skin: bool,
+    msaa_samples: u8,
+    marker: PhantomData<B>,
+}
+
+impl<B: Backend> DrawCustomDesc<B> {
+    /// Create instance of `DrawCustomDesc` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Also draw meshes with `JointTransforms` through the skinned vertex shader.
+    pub fn with_skinning(mut self, skinning: bool) -> Self {
+        self.skinning = skinning;
+        self
+    }
+
+    /// Draw only `Transparent` meshes, back to front, with alpha blending.
+    pub fn with_transparency(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Draw only `OnionGhost`s, as blended outlines through the skinned vertex shader.
+    pub fn with_onion_skin(mut self, onion_skin: bool) -> Self {
+        self.onion_skin = onion_skin;
+        self
+    }
+
+    /// Match the sample count of a multisampled target.
+    pub fn with_msaa_samples(mut self, samples: u8) -> Self {
+        self.msaa_samples = samples;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawCustomDesc<B> {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _world: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        let env = EnvironmentSub::new(
+            factory,
+            [
+                hal::pso::ShaderStageFlags::VERTEX,
+                hal::pso::ShaderStageFlags::FRAGMENT,
+            ],
+        )?;
+        let materials = MaterialSub::new(factory)?;
+        let skinning = SkinningSub::new(factory)?;
+        let npr = DynamicUniform::new(
+            factory,
+            pso::ShaderStageFlags::VERTEX | pso::ShaderStageFlags::FRAGMENT,
+        )?;
+
+        let mut vertex_format_base = CustomPassDef::base_format();
+        let mut vertex_format_skinned = CustomPassDef::skinned_format();
+
+        let (mut pipelines, pipeline_layout) = build_custom_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format_base,
+            &vertex_format_skinned,
+            self.skinning || self.onion_skin,
+            self.transparent || self.onion_skin,
+            self.msaa_samples,
+            if self.onion_skin {
+                &*ONION_FRAGMENT
+            } else {
+                CustomPassDef::fragment_shader()
+            },
+            vec![
+                env.raw_layout(),
+                materials.raw_layout(),
+                skinning.raw_layout(),
+                npr.raw_layout(),
+            ],
+        )?;
+
+        // Meshes bind their vertex buffers in sorted format order.
+        vertex_format_base.sort();
+        vertex_format_skinned.sort();
+
+        Ok(Box::new(DrawCustom::<B> {
+            pipeline_basic: pipelines.remove(0),
+            pipeline_skinned: pipelines.pop(),
+            pipeline_layout,
+            transparent: self.transparent,
+            onion_skin: self.onion_skin,
+            static_batches: Default::default(),
+            skinned_batches: Default::default(),
+            static_ordered: Default::default(),
+            skinned_ordered: Default::default(),
+            vertex_format_base,
+            vertex_format_skinned,
+            env,
+            materials,
+            skinning,
+            npr,
+            models: DynamicVertexBuffer::new(),
+            skinned_models: DynamicVertexBuffer::new(),
+        }))
+    }
+}
+
+/// Draws meshes with the cel shading fragment shader and the NPR uniform bound at set 3.
+#[derive(Debug)]
+pub struct DrawCustom<B: Backend> {
+    pipeline_basic: B::GraphicsPipeline,
+    pipeline_skinned: Option<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    transparent: bool,
+    onion_skin: bool,
+    static_batches: TwoLevelBatch<MaterialId, u32, SmallVec<[NprVertexArgs; 4]>>,
+    skinned_batches: TwoLevelBatch<MaterialId, u32, SmallVec<[NprSkinnedVertexArgs; 4]>>,
+    // Transparent meshes must keep the back to front order computed by `Visibility`
+    static_ordered: OrderedOneLevelBatch<(MaterialId, u32), NprVertexArgs>,
+    skinned_ordered: OrderedOneLevelBatch<(MaterialId, u32), NprSkinnedVertexArgs>,
+    vertex_format_base: Vec<VertexFormat>,
+    vertex_format_skinned: Vec<VertexFormat>,
+    env: EnvironmentSub<B>,
+    materials: MaterialSub<B, FullTextureSet>,
+    skinning: SkinningSub<B>,
+    npr: DynamicUniform<B, NprUniformArgs>,
+    models: DynamicVertexBuffer<B, NprVertexArgs>,
+    skinned_models: DynamicVertexBuffer<B, NprSkinnedVertexArgs>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawCustom<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        let (
+            mesh_storage,
+            visibility,
+            meshes,
+            materials,
+            transforms,
+            joints,
+            tints,
+            ghosts,
+            deformations,
+            softnesses,
+        ) = <(
+                Read<'_, AssetStorage<Mesh>>,
+                ReadExpect<'_, Visibility>,
+                ReadStorage<'_, Handle<Mesh>>,
+                ReadStorage<'_, Handle<Material>>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, JointTransforms>,
+                ReadStorage<'_, Tint>,
+                ReadStorage<'_, OnionGhost>,
+                ReadStorage<'_, Deformation>,
+                ReadStorage<'_, InheritedBandSoftness>,
+            )>::fetch(world);
+
+        self.env.process(factory, index, world);
+        self.npr
+            .write(factory, index, NprUniformArgs::from_world(world).std140());
+        self.materials.maintain();
+        self.skinning.maintain();
+
+        let materials_ref = &mut self.materials;
+        let skinning_ref = &mut self.skinning;
+
+        if self.onion_skin {
+            self.static_batches.clear_inner();
+            self.skinned_batches.clear_inner();
+            let skinned_ref = &mut self.skinned_batches;
+
+            // Ghosts are posed in world space and `Hidden` from visibility sorting.
+            (&materials, &meshes, &transforms, tints.maybe(), &joints, &ghosts)
+                .join()
+                .map(|(mat, mesh, tform, tint, joints, _)| {
+                    (
+                        (mat, mesh.id()),
+                        NprSkinnedVertexArgs::from_object_data(
+                            tform,
+                            tint,
+                            None,
+                            None,
+                            skinning_ref.insert(joints),
+                        ),
+                    )
+                })
+                .for_each_group(|(mat, mesh_id), data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        if let Some((mat, _)) = materials_ref.insert(factory, world, mat) {
+                            skinned_ref.insert(mat, mesh_id, data.drain(..));
+                        }
+                    }
+                });
 
-/// A `RenderPlugin` for forward rendering of 3d objects using physically-based shading.
-pub type RenderCustom3D = RenderBase3D<CustomPassDef>;
\ No newline at end of file
+            self.static_batches.prune();
+            self.skinned_batches.prune();
+
+            self.models.write(factory, index, 0, self.static_batches.data());
+            self.skinned_models.write(
+                factory,
+                index,
+                self.skinned_batches.count() as u64,
+                self.skinned_batches.data(),
+            );
+        } else if self.transparent {
+            self.static_ordered.swap_clear();
+            self.skinned_ordered.swap_clear();
+            let statics_ref = &mut self.static_ordered;
+            let skinned_ref = &mut self.skinned_ordered;
+            let mut joined = (
+                (
+                    &materials,
+                    &meshes,
+                    &transforms,
+                    tints.maybe(),
+                    deformations.maybe(),
+                    softnesses.maybe(),
+                ),
+                !&joints,
+            )
+                .join();
+            let mut skinned_joined = (
+                &materials,
+                &meshes,
+                &transforms,
+                tints.maybe(),
+                deformations.maybe(),
+                softnesses.maybe(),
+                &joints,
+            )
+                .join();
+
+            visibility
+                .visible_ordered
+                .iter()
+                .filter_map(|e| joined.get_unchecked(e.id()))
+                .map(|((mat, mesh, tform, tint, deformation, softness), _)| {
+                    (
+                        (mat, mesh.id()),
+                        NprVertexArgs::from_object_data(tform, tint, deformation, softness),
+                    )
+                })
+                .for_each_group(|(mat, mesh_id), data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        if let Some((mat, _)) = materials_ref.insert(factory, world, mat) {
+                            statics_ref.insert((mat, mesh_id), data.drain(..));
+                        }
+                    }
+                });
+
+            if self.pipeline_skinned.is_some() {
+                visibility
+                    .visible_ordered
+                    .iter()
+                    .filter_map(|e| skinned_joined.get_unchecked(e.id()))
+                    .map(|(mat, mesh, tform, tint, deformation, softness, joints)| {
+                        (
+                            (mat, mesh.id()),
+                            NprSkinnedVertexArgs::from_object_data(
+                                tform,
+                                tint,
+                                deformation,
+                                softness,
+                                skinning_ref.insert(joints),
+                            ),
+                        )
+                    })
+                    .for_each_group(|(mat, mesh_id), data| {
+                        if mesh_storage.contains_id(mesh_id) {
+                            if let Some((mat, _)) = materials_ref.insert(factory, world, mat) {
+                                skinned_ref.insert((mat, mesh_id), data.drain(..));
+                            }
+                        }
+                    });
+            }
+
+            self.models.write(
+                factory,
+                index,
+                self.static_ordered.count() as u64,
+                Some(self.static_ordered.data()),
+            );
+            self.skinned_models.write(
+                factory,
+                index,
+                self.skinned_ordered.count() as u64,
+                Some(self.skinned_ordered.data()),
+            );
+        } else {
+            self.static_batches.clear_inner();
+            self.skinned_batches.clear_inner();
+            let statics_ref = &mut self.static_batches;
+            let skinned_ref = &mut self.skinned_batches;
+
+            (
+                (
+                    &materials,
+                    &meshes,
+                    &transforms,
+                    tints.maybe(),
+                    deformations.maybe(),
+                    softnesses.maybe(),
+                ),
+                (!&joints, !&ghosts),
+                &visibility.visible_unordered,
+            )
+                .join()
+                .map(|((mat, mesh, tform, tint, deformation, softness), _, _)| {
+                    (
+                        (mat, mesh.id()),
+                        NprVertexArgs::from_object_data(tform, tint, deformation, softness),
+                    )
+                })
+                .for_each_group(|(mat, mesh_id), data| {
+                    if mesh_storage.contains_id(mesh_id) {
+                        if let Some((mat, _)) = materials_ref.insert(factory, world, mat) {
+                            statics_ref.insert(mat, mesh_id, data.drain(..));
+                        }
+                    }
+                });
+
+            if self.pipeline_skinned.is_some() {
+                (
+                    (
+                        &materials,
+                        &meshes,
+                        &transforms,
+                        tints.maybe(),
+                        deformations.maybe(),
+                        softnesses.maybe(),
+                    ),
+                    (&joints, !&ghosts),
+                    &visibility.visible_unordered,
+                )
+                    .join()
+                    .map(|((mat, mesh, tform, tint, deformation, softness), (joints, _), _)| {
+                        (
+                            (mat, mesh.id()),
+                            NprSkinnedVertexArgs::from_object_data(
+                                tform,
+                                tint,
+                                deformation,
+                                softness,
+                                skinning_ref.insert(joints),
+                            ),
+                        )
+                    })
+                    .for_each_group(|(mat, mesh_id), data| {
+                        if mesh_storage.contains_id(mesh_id) {
+                            if let Some((mat, _)) = materials_ref.insert(factory, world, mat) {
+                                skinned_ref.insert(mat, mesh_id, data.drain(..));
+                            }
+                        }
+                    });
+            }
+
+            self.static_batches.prune();
+            self.skinned_batches.prune();
+
+            self.models.write(
+                factory,
+                index,
+                self.static_batches.count() as u64,
+                self.static_batches.data(),
+            );
+            self.skinned_models.write(
+                factory,
+                index,
+                self.skinned_batches.count() as u64,
+                self.skinned_batches.data(),
+            );
+        }
+        self.skinning.commit(factory, index);
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(world);
+        let models_loc = self.vertex_format_base.len() as u32;
+        let skin_models_loc = self.vertex_format_skinned.len() as u32;
+
+        encoder.bind_graphics_pipeline(&self.pipeline_basic);
+        self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+        self.npr.bind(index, &self.pipeline_layout, 3, &mut encoder);
+
+        if self.models.bind(index, models_loc, 0, &mut encoder) {
+            let draws = if self.transparent {
+                ordered_ranges(&self.static_ordered)
+            } else {
+                grouped_ranges(&self.static_batches)
+            };
+            for (mat_id, mesh_id, instances) in draws {
+                if self.materials.loaded(mat_id) {
+                    self.materials
+                        .bind(&self.pipeline_layout, 1, mat_id, &mut encoder);
+                    if let Some(mesh) =
+                        B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(mesh_id) })
+                    {
+                        mesh.bind_and_draw(0, &self.vertex_format_base, instances, &mut encoder)
+                            .unwrap();
+                    }
+                }
+            }
+        }
+
+        if let Some(pipeline_skinned) = self.pipeline_skinned.as_ref() {
+            encoder.bind_graphics_pipeline(pipeline_skinned);
+
+            if self
+                .skinned_models
+                .bind(index, skin_models_loc, 0, &mut encoder)
+            {
+                self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+                self.skinning
+                    .bind(index, &self.pipeline_layout, 2, &mut encoder);
+                self.npr.bind(index, &self.pipeline_layout, 3, &mut encoder);
+
+                let draws = if self.transparent {
+                    ordered_ranges(&self.skinned_ordered)
+                } else {
+                    grouped_ranges(&self.skinned_batches)
+                };
+                for (mat_id, mesh_id, instances) in draws {
+                    if self.materials.loaded(mat_id) {
+                        self.materials
+                            .bind(&self.pipeline_layout, 1, mat_id, &mut encoder);
+                        if let Some(mesh) =
+                            B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(mesh_id) })
+                        {
+                            mesh.bind_and_draw(
+                                0,
+                                &self.vertex_format_skinned,
+                                instances,
+                                &mut encoder,
+                            )
+                            .unwrap();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.pipeline_basic);
+            if let Some(pipeline) = self.pipeline_skinned {
+                factory.device().destroy_graphics_pipeline(pipeline);
+            }
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Instance ranges of a back to front ordered batch, one per draw call.
+fn ordered_ranges<T>(
+    batch: &OrderedOneLevelBatch<(MaterialId, u32), T>,
+) -> Vec<(MaterialId, u32, Range<u32>)> {
+    batch
+        .iter()
+        .map(|(&(mat, mesh), instances)| (mat, mesh, instances))
+        .collect()
+}
+
+/// Instance ranges of a batch grouped by material then mesh, one per draw call.
+fn grouped_ranges<T>(
+    batch: &TwoLevelBatch<MaterialId, u32, SmallVec<[T; 4]>>,
+) -> Vec<(MaterialId, u32, Range<u32>)> {
+    let mut start = 0;
+    let mut ranges = Vec::new();
+    for (&mat, meshes) in batch.iter() {
+        for (mesh, data) in meshes {
+            let end = start + data.len() as u32;
+            ranges.push((mat, *mesh, start..end));
+            start = end;
+        }
+    }
+    ranges
+}
+
+fn build_custom_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    vertex_format_base: &[VertexFormat],
+    vertex_format_skinned: &[VertexFormat],
+    skinning: bool,
+    transparent: bool,
+    msaa_samples: u8,
+    fragment_shader: &SpirvShader,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let vertex_desc = |formats: &[VertexFormat], instance: VertexFormat| {
+        formats
+            .iter()
+            .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+            .chain(Some((instance, pso::VertexInputRate::Instance(1))))
+            .collect::<Vec<_>>()
+    };
+    let base_desc = vertex_desc(vertex_format_base, NprVertexArgs::vertex());
+    let skinned_desc = vertex_desc(vertex_format_skinned, NprSkinnedVertexArgs::vertex());
+
+    let shader_vertex_basic = unsafe { CustomPassDef::vertex_shader().module(factory).unwrap() };
+    let shader_fragment = unsafe { fragment_shader.module(factory).unwrap() };
+
+    let pipe_desc = PipelineDescBuilder::new()
+        .with_vertex_desc(&base_desc)
+        .with_shaders(util::simple_shader_set(
+            &shader_vertex_basic,
+            Some(&shader_fragment),
+        ))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_face_culling(pso::Face::BACK)
+        .with_multisampling(multisampling(msaa_samples))
+        .with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::Less,
+            write: !transparent,
+        })
+        .with_blend_targets(vec![pso::ColorBlendDesc {
+            mask: pso::ColorMask::ALL,
+            blend: if transparent {
+                Some(pso::BlendState::PREMULTIPLIED_ALPHA)
+            } else {
+                None
+            },
+        }]);
+
+    let pipelines = if skinning {
+        let shader_vertex_skinned =
+            unsafe { CustomPassDef::vertex_skinned_shader().module(factory).unwrap() };
+
+        let pipes = PipelinesBuilder::new()
+            .with_pipeline(pipe_desc.clone())
+            .with_child_pipeline(
+                0,
+                pipe_desc
+                    .with_vertex_desc(&skinned_desc)
+                    .with_shaders(util::simple_shader_set(
+                        &shader_vertex_skinned,
+                        Some(&shader_fragment),
+                    )),
+            )
+            .build(factory, None);
+
+        unsafe {
+            factory.destroy_shader_module(shader_vertex_skinned);
+        }
+
+        pipes
+    } else {
+        PipelinesBuilder::new()
+            .with_pipeline(pipe_desc)
+            .build(factory, None)
+    };
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex_basic);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipelines {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(pipelines) => Ok((pipelines, pipeline_layout)),
+    }
+}
+
+/// A `RenderPlugin` for forward rendering of 3d objects using cel shading.
+#[derive(Default, Debug)]
+pub struct RenderCustom3D {
+    target: Target,
+    skinning: bool,
+    msaa_samples: u8,
+}
+
+impl RenderCustom3D {
+    /// Set target to which 3d meshes will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Enable rendering for skinned meshes.
+    pub fn with_skinning(mut self) -> Self {
+        self.skinning = true;
+        self
+    }
+
+    /// Set the samples per pixel of the target, which must match how it was defined.
+    pub fn with_msaa_samples(mut self, samples: u8) -> Self {
+        self.msaa_samples = samples;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderCustom3D {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(VisibilitySortingSystem::new(), "visibility_system", &[]);
+        world.entry::<LineBoil>().or_insert_with(Default::default);
+        world.entry::<DebugView>().or_insert_with(Default::default);
+        world.entry::<CelStyle>().or_insert_with(Default::default);
+        world.register::<OnionGhost>();
+        world.register::<BandSoftness>();
+        builder.add(SquashStretchSystem, "squash_stretch", &[]);
+        builder.add(BandSoftnessSystem, "band_softness", &[]);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let skinning = self.skinning;
+        let samples = self.msaa_samples;
+        plan.extend_target(self.target, move |ctx| {
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawCustomDesc::<B>::new()
+                    .with_skinning(skinning)
+                    .with_msaa_samples(samples)
+                    .builder(),
+            )?;
+            ctx.add(
+                RenderOrder::Transparent,
+                DrawCustomDesc::<B>::new()
+                    .with_skinning(skinning)
+                    .with_transparency(true)
+                    .with_msaa_samples(samples)
+                    .builder(),
+            )?;
+            if skinning {
+                ctx.add(
+                    RenderOrder::Transparent,
+                    DrawCustomDesc::<B>::new()
+                        .with_onion_skin(true)
+                        .with_msaa_samples(samples)
+                        .builder(),
+                )?;
+            }
+            Ok(())
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// Replaces the cel shaded output of `CustomPassDef` with one stage of the shading pipeline.
+///
+/// Passed to the fragment shader in `NprUniformArgs`, so switching views never rebuilds the
+/// pipelines. Keep the order in sync with the `DEBUG_*` constants in `outline.frag`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DebugView {
+    /// Regular cel shading.
+    Off,
+    /// World space normals, remapped to colors.
+    Normals,
+    /// Summed diffuse lighting before it is quantized into bands.
+    Lighting,
+    /// Lighting after quantization, without albedo.
+    Bands,
+    /// Albedo texture and tint only.
+    Albedo,
+    /// Where the N·V outline threshold draws the rim.
+    Rim,
+    /// Distance to the camera between the near (white) and far (black) planes.
+    Depth,
+    /// Checkerboard over the texture coordinates, for spotting UV stretching and seams.
+    UvChecker,
+    /// Number of lights reaching each fragment, from blue (none) to red (all).
+    LightCount,
+}
+
+impl Default for DebugView {
+    fn default() -> Self {
+        DebugView::Off
+    }
+}
+
+impl DebugView {
+    const ALL: [DebugView; 9] = [
+        DebugView::Off,
+        DebugView::Normals,
+        DebugView::Lighting,
+        DebugView::Bands,
+        DebugView::Albedo,
+        DebugView::Rim,
+        DebugView::Depth,
+        DebugView::UvChecker,
+        DebugView::LightCount,
+    ];
+
+    /// Value of the shader's `debug_view` uniform.
+    pub fn index(self) -> i32 {
+        Self::ALL.iter().position(|&view| view == self).unwrap_or(0) as i32
+    }
+
+    /// The next view, wrapping around to `Off`.
+    pub fn next(self) -> Self {
+        Self::ALL[(self.index() as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Off => "off",
+            DebugView::Normals => "normals",
+            DebugView::Lighting => "N·L before quantization",
+            DebugView::Bands => "light bands",
+            DebugView::Albedo => "albedo",
+            DebugView::Rim => "rim mask",
+            DebugView::Depth => "depth",
+            DebugView::UvChecker => "UV checker",
+            DebugView::LightCount => "light count",
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    core::{
+        ecs::{Builder, Entity, Join, Read, ReadStorage, System, World, WorldExt, WriteStorage},
+        math::{UnitQuaternion, Vector3},
+    },
+    renderer::light::{DirectionalLight, Light},
+    ui::{get_default_font, Anchor, FontAsset, LineMode, UiText, UiTransform},
+    utils::fps_counter::FpsCounter,
+};
+
+use crate::{
+    antialiasing::Antialiasing,
+    boil::LineBoil,
+    debug_view::DebugView,
+    onion::OnionSkin,
+    strokes::StrokeStyle,
+    style::{CelStyle, PostEffects},
+};
+
+const LINE_HEIGHT: f32 = 20.0;
+const FONT_SIZE: f32 = 16.0;
+/// Lines above the parameter list: FPS, model, animation, style, debug view and
+/// anti-aliasing.
+const STATUS_LINES: usize = 6;
+/// Lines of the model list shown below the parameters, including its heading.
+const MODEL_LIST_LINES: usize = 13;
+
+/// NPR parameter that can be edited from the HUD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HudParam {
+    StrokeWidth,
+    InnerLineScale,
+    StrokeJitter,
+    BoilRate,
+    BoilAmplitude,
+    BoilFrequency,
+    OnionOpacity,
+    BandCount,
+    BandSoftness,
+    OutlineThreshold,
+    LightDirection,
+    LightIntensity,
+}
+
+impl HudParam {
+    pub const ALL: [HudParam; 12] = [
+        HudParam::StrokeWidth,
+        HudParam::InnerLineScale,
+        HudParam::StrokeJitter,
+        HudParam::BoilRate,
+        HudParam::BoilAmplitude,
+        HudParam::BoilFrequency,
+        HudParam::OnionOpacity,
+        HudParam::BandCount,
+        HudParam::BandSoftness,
+        HudParam::OutlineThreshold,
+        HudParam::LightDirection,
+        HudParam::LightIntensity,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HudParam::StrokeWidth => "stroke width",
+            HudParam::InnerLineScale => "inner line scale",
+            HudParam::StrokeJitter => "stroke jitter",
+            HudParam::BoilRate => "boil rate",
+            HudParam::BoilAmplitude => "boil amplitude",
+            HudParam::BoilFrequency => "boil frequency",
+            HudParam::OnionOpacity => "onion opacity",
+            HudParam::BandCount => "light bands",
+            HudParam::BandSoftness => "band softness",
+            HudParam::OutlineThreshold => "outline threshold",
+            HudParam::LightDirection => "key light direction",
+            HudParam::LightIntensity => "key light intensity",
+        }
+    }
+
+    fn value(
+        self,
+        style: &StrokeStyle,
+        boil: &LineBoil,
+        onion: &OnionSkin,
+        cel: &CelStyle,
+        key: Option<&DirectionalLight>,
+    ) -> String {
+        match self {
+            HudParam::StrokeWidth => format!("{:.3}", style.width),
+            HudParam::InnerLineScale => format!("{:.2}", style.inner_line_scale),
+            HudParam::StrokeJitter => format!("{:.3}", style.jitter),
+            HudParam::BoilRate => format!("on {}s", boil.rate),
+            HudParam::BoilAmplitude => format!("{:.3}", boil.outline_amplitude),
+            HudParam::BoilFrequency => format!("{:.2}", boil.frequency),
+            HudParam::OnionOpacity => format!("{:.2}", onion.opacity),
+            HudParam::BandCount => cel.bands.len().to_string(),
+            HudParam::BandSoftness if cel.smooth_bands => format!("{:.2} px", cel.band_softness),
+            HudParam::BandSoftness => "hard".to_string(),
+            HudParam::OutlineThreshold => format!("{:.2}", cel.outline.threshold),
+            HudParam::LightDirection => key.map_or_else(
+                || "no key light".to_string(),
+                |key| {
+                    let yaw = key.direction.x.atan2(key.direction.z).to_degrees();
+                    format!("{:.0} deg", yaw)
+                },
+            ),
+            HudParam::LightIntensity => key.map_or_else(
+                || "no key light".to_string(),
+                |key| format!("{:.2}", key.intensity),
+            ),
+        }
+    }
+
+    /// Moves the parameter `steps` notches up or down: scales continuous values by 10% per
+    /// notch, steps counts and opacities linearly, and turns the key light, the first
+    /// directional light, by 5 degrees about the vertical axis.
+    pub fn adjust(self, world: &World, steps: i32) {
+        let scale = 1.1f32.powi(steps);
+        let mut style = world.write_resource::<StrokeStyle>();
+        let mut boil = world.write_resource::<LineBoil>();
+        let mut onion = world.write_resource::<OnionSkin>();
+        let mut cel = world.write_resource::<CelStyle>();
+        let mut lights = world.write_storage::<Light>();
+        let key = (&mut lights).join().find_map(|light| match light {
+            Light::Directional(directional) => Some(directional),
+            _ => None,
+        });
+        match self {
+            HudParam::StrokeWidth => style.width = (style.width * scale).max(1.0e-4),
+            HudParam::InnerLineScale => {
+                style.inner_line_scale = (style.inner_line_scale * scale).max(1.0e-2)
+            }
+            HudParam::StrokeJitter => style.jitter = (style.jitter * scale).max(1.0e-4),
+            HudParam::BoilRate => boil.rate = (boil.rate as i32 + steps).max(1) as u32,
+            HudParam::BoilAmplitude => {
+                boil.outline_amplitude = (boil.outline_amplitude * scale).max(1.0e-4)
+            }
+            HudParam::BoilFrequency => boil.frequency = (boil.frequency * scale).max(1.0e-2),
+            HudParam::OnionOpacity => {
+                onion.opacity = (onion.opacity + 0.05 * steps as f32).max(0.0).min(1.0)
+            }
+            HudParam::BandCount => {
+                for _ in 0..steps.abs() {
+                    if steps > 0 {
+                        cel.add_band();
+                    } else {
+                        cel.remove_band();
+                    }
+                }
+            }
+            HudParam::BandSoftness => {
+                let softness = if cel.smooth_bands {
+                    cel.band_softness
+                } else {
+                    0.0
+                };
+                cel.band_softness = (softness + 0.25 * steps as f32).max(0.0);
+                // Softening hard bands turns smoothing on, and back off once it reaches 0.
+                cel.smooth_bands = cel.band_softness > 0.0;
+            }
+            HudParam::OutlineThreshold => {
+                cel.outline.threshold = (cel.outline.threshold + 0.01 * steps as f32)
+                    .max(0.0)
+                    .min(1.0)
+            }
+            HudParam::LightDirection => {
+                if let Some(key) = key {
+                    let turn = UnitQuaternion::from_axis_angle(
+                        &Vector3::y_axis(),
+                        (5.0 * steps as f32).to_radians(),
+                    );
+                    key.direction = turn * key.direction;
+                }
+            }
+            HudParam::LightIntensity => {
+                if let Some(key) = key {
+                    key.intensity = (key.intensity * scale).max(1.0e-3);
+                }
+            }
+        }
+    }
+}
+
+/// On-screen overlay with the frame rate, what is being shown and the live NPR parameters.
+///
+/// The application fills in `model`, `animation`, `style` and `models`; `HudSystem` writes
+/// the text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Hud {
+    pub visible: bool,
+    pub model: String,
+    pub animation: String,
+    pub style: String,
+    /// Whether the model list is shown below the parameters.
+    pub show_models: bool,
+    /// Heading and rows of the model list, at most `MODEL_LIST_LINES` in total.
+    pub models: Vec<String>,
+    /// Index into `HudParam::ALL` of the parameter the keyboard sliders edit.
+    pub selected: usize,
+    lines: Vec<Entity>,
+}
+
+impl Hud {
+    /// Creates the text entities of the overlay in the top left corner of the window.
+    pub fn create(world: &mut World) {
+        let font = get_default_font(
+            &world.read_resource::<Loader>(),
+            &world.read_resource::<AssetStorage<FontAsset>>(),
+        );
+        let lines = (0..STATUS_LINES + HudParam::ALL.len() + MODEL_LIST_LINES)
+            .map(|line| {
+                let y = -(line as f32 + 0.5) * LINE_HEIGHT - 8.0;
+                world
+                    .create_entity()
+                    .with(UiTransform::new(
+                        format!("hud_line_{}", line),
+                        Anchor::TopLeft,
+                        Anchor::TopLeft,
+                        12.0,
+                        y,
+                        1.0,
+                        600.0,
+                        LINE_HEIGHT,
+                    ))
+                    .with(UiText::new(
+                        font.clone(),
+                        String::new(),
+                        [1.0, 1.0, 1.0, 1.0],
+                        FONT_SIZE,
+                        LineMode::Single,
+                        Anchor::MiddleLeft,
+                    ))
+                    .build()
+            })
+            .collect();
+
+        let mut hud = world.entry::<Hud>().or_insert_with(Default::default);
+        hud.visible = true;
+        hud.lines = lines;
+    }
+
+    /// Number of model list entries that fit below its heading.
+    pub fn model_rows(&self) -> usize {
+        MODEL_LIST_LINES - 1
+    }
+
+    pub fn selected_param(&self) -> HudParam {
+        HudParam::ALL[self.selected % HudParam::ALL.len()]
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % HudParam::ALL.len();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = (self.selected + HudParam::ALL.len() - 1) % HudParam::ALL.len();
+    }
+}
+
+/// Writes the `Hud`'s text every frame.
+#[derive(Debug, Default)]
+pub struct HudSystem;
+
+impl<'a> System<'a> for HudSystem {
+    type SystemData = (
+        Read<'a, Hud>,
+        Read<'a, FpsCounter>,
+        Read<'a, StrokeStyle>,
+        Read<'a, LineBoil>,
+        Read<'a, OnionSkin>,
+        Read<'a, DebugView>,
+        Read<'a, CelStyle>,
+        Read<'a, Antialiasing>,
+        Read<'a, PostEffects>,
+        ReadStorage<'a, Light>,
+        WriteStorage<'a, UiText>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            hud,
+            fps,
+            style,
+            boil,
+            onion,
+            debug_view,
+            cel,
+            antialiasing,
+            post_effects,
+            lights,
+            mut texts,
+        ): Self::SystemData,
+    ) {
+        let mut content = vec![
+            format!("FPS: {:.0}", fps.sampled_fps()),
+            format!("Model: {}", hud.model),
+            format!("Animation: {}", hud.animation),
+            format!("Style: {}", hud.style),
+            format!("Debug view: {}", debug_view.name()),
+            format!("Anti-aliasing: {}", antialiasing.name(&post_effects)),
+        ];
+        let key = lights.join().find_map(|light| match light {
+            Light::Directional(directional) => Some(directional),
+            _ => None,
+        });
+        let selected = hud.selected_param();
+        content.extend(HudParam::ALL.iter().map(|&param| {
+            let marker = if param == selected { '>' } else { ' ' };
+            let value = param.value(&style, &boil, &onion, &cel, key);
+            format!("{} {}: {}", marker, param.name(), value)
+        }));
+        if hud.show_models {
+            content.extend(hud.models.iter().cloned());
+        }
+
+        // Lines past the content are cleared, so a hidden model list leaves no leftovers.
+        let mut content = content.into_iter();
+        for &line in &hud.lines {
+            let text = content.next().unwrap_or_default();
+            if let Some(ui_text) = texts.get_mut(line) {
+                ui_text.text = if hud.visible { text } else { String::new() };
+            }
+        }
+    }
+}
@@ -1 +1,18 @@
+pub mod animation;
+pub mod antialiasing;
+pub mod boil;
+pub mod browser;
+pub mod camera;
 pub mod custom_render;
+pub mod debug_view;
+pub mod hud;
+pub mod lighting;
+pub mod lines;
+pub mod obj;
+pub mod onion;
+pub mod options;
+pub mod settings;
+pub mod squash;
+pub mod strokes;
+pub mod style;
+pub mod svg;
@@ -0,0 +1,428 @@
+use amethyst::{
+    core::{
+        ecs::{
+            Builder, Entities, Entity, Join, Read, ReadStorage, System, World, WorldExt, Write,
+            WriteStorage,
+        },
+        math::{Point3, UnitQuaternion, Vector3},
+        Transform,
+    },
+    input::{BindingTypes, InputHandler},
+    renderer::{
+        debug_drawing::DebugLines,
+        light::{DirectionalLight, Light, PointLight},
+        palette::{Srgb, Srgba},
+        resources::AmbientColor,
+    },
+    winit::MouseButton,
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+use std::marker::PhantomData;
+
+/// Colors `cycle_color` steps through.
+const LIGHT_COLORS: [[f32; 3]; 6] = [
+    [1.0, 1.0, 1.0],
+    [1.0, 0.85, 0.7],
+    [0.75, 0.85, 1.0],
+    [1.0, 0.55, 0.45],
+    [0.55, 1.0, 0.6],
+    [0.7, 0.55, 1.0],
+];
+
+/// The directional light that shapes the cel shading bands.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KeyLight {
+    /// Direction the light travels in, need not be normalized.
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+impl Default for KeyLight {
+    fn default() -> Self {
+        KeyLight {
+            direction: [1.0, 1.0, 1.0],
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RigPointLight {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub radius: f32,
+}
+
+impl Default for RigPointLight {
+    fn default() -> Self {
+        RigPointLight {
+            position: [0.0, 5.0, 5.0],
+            color: [1.0, 1.0, 1.0],
+            intensity: 20.0,
+            radius: 1.0,
+        }
+    }
+}
+
+/// Every light in the scene, saved to and loaded from RON with `amethyst::config::Config`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LightRig {
+    pub ambient_color: [f32; 4],
+    pub key: Option<KeyLight>,
+    pub points: Vec<RigPointLight>,
+}
+
+impl Default for LightRig {
+    /// The rig of `prefabs/scene.ron`.
+    fn default() -> Self {
+        LightRig {
+            ambient_color: [0.4, 0.4, 0.4, 0.5],
+            key: Some(KeyLight::default()),
+            points: Vec::new(),
+        }
+    }
+}
+
+impl LightRig {
+    /// Reads the current lights out of `world`. The first directional light is the key light;
+    /// any other directional lights are left out.
+    pub fn capture(world: &World) -> Self {
+        let ambient = world.read_resource::<AmbientColor>().0;
+        let lights = world.read_storage::<Light>();
+        let transforms = world.read_storage::<Transform>();
+
+        let mut rig = LightRig {
+            ambient_color: [ambient.red, ambient.green, ambient.blue, ambient.alpha],
+            key: None,
+            points: Vec::new(),
+        };
+        for (light, transform) in (&lights, (&transforms).maybe()).join() {
+            match light {
+                Light::Directional(directional) if rig.key.is_none() => {
+                    rig.key = Some(KeyLight {
+                        direction: directional.direction.into(),
+                        color: rgb(directional.color),
+                        intensity: directional.intensity,
+                    });
+                }
+                Light::Point(point) => {
+                    let position = transform.map_or([0.0; 3], |t| (*t.translation()).into());
+                    rig.points.push(RigPointLight {
+                        position,
+                        color: rgb(point.color),
+                        intensity: point.intensity,
+                        radius: point.radius,
+                    });
+                }
+                _ => {}
+            }
+        }
+        rig
+    }
+
+    /// Replaces every light in `world` with the rig's.
+    pub fn spawn(&self, world: &mut World) {
+        let existing = {
+            let entities = world.entities();
+            let lights = world.read_storage::<Light>();
+            (&*entities, &lights).join().map(|(e, _)| e).collect::<Vec<_>>()
+        };
+        if let Err(e) = world.delete_entities(&existing) {
+            eprintln!("Could not remove the previous lights: {}", e);
+        }
+
+        let [r, g, b, a] = self.ambient_color;
+        world.insert(AmbientColor(Srgba::new(r, g, b, a)));
+        if let Some(key) = &self.key {
+            world
+                .create_entity()
+                .with(Light::Directional(DirectionalLight {
+                    color: srgb(key.color),
+                    direction: Vector3::from(key.direction).normalize(),
+                    intensity: key.intensity,
+                }))
+                .build();
+        }
+        for point in &self.points {
+            spawn_point_light(world, point);
+        }
+    }
+}
+
+fn rgb(color: Srgb) -> [f32; 3] {
+    [color.red, color.green, color.blue]
+}
+
+fn srgb([r, g, b]: [f32; 3]) -> Srgb {
+    Srgb::new(r, g, b)
+}
+
+fn spawn_point_light(world: &mut World, point: &RigPointLight) -> Entity {
+    let mut transform = Transform::default();
+    transform.set_translation(Vector3::from(point.position));
+    world
+        .create_entity()
+        .with(Light::Point(PointLight {
+            color: srgb(point.color),
+            intensity: point.intensity,
+            radius: point.radius,
+            ..Default::default()
+        }))
+        .with(transform)
+        .build()
+}
+
+/// Runtime lighting controls: the light being edited and how its gizmos are drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightEditor {
+    /// Draw gizmos for every light, and accept mouse edits.
+    pub enabled: bool,
+    /// Light edited by the keyboard controls; the key light when unset.
+    pub selected: Option<Entity>,
+    /// Radians the key light turns per pixel dragged with the right mouse button.
+    pub rotate_sensitivity: f32,
+    /// Length of the key light's arrow and size of the point light markers, in world units.
+    pub gizmo_size: f32,
+    last_mouse: Option<(f32, f32)>,
+}
+
+impl Default for LightEditor {
+    fn default() -> Self {
+        LightEditor {
+            enabled: false,
+            selected: None,
+            rotate_sensitivity: 0.01,
+            gizmo_size: 5.0,
+            last_mouse: None,
+        }
+    }
+}
+
+impl LightEditor {
+    /// The selected light if it still exists, or else the key light.
+    pub fn target(&self, world: &World) -> Option<Entity> {
+        let entities = world.entities();
+        let lights = world.read_storage::<Light>();
+        self.selected
+            .filter(|&entity| entities.is_alive(entity) && lights.contains(entity))
+            .or_else(|| key_light(&entities, &lights))
+    }
+
+    /// Selects the next light, in entity order.
+    pub fn select_next(&mut self, world: &World) {
+        let current = self.target(world);
+        let entities = world.entities();
+        let lights = world.read_storage::<Light>();
+        let all = (&*entities, &lights).join().map(|(e, _)| e).collect::<Vec<_>>();
+        let next = current
+            .and_then(|current| all.iter().position(|&e| e == current))
+            .map_or(0, |index| (index + 1) % all.len().max(1));
+        self.selected = all.get(next).copied();
+        if let Some(light) = self.selected.and_then(|e| lights.get(e)) {
+            println!("Selected {} light {}", light_kind(light), next);
+        }
+    }
+
+    /// Multiplies the selected light's intensity by `factor`.
+    pub fn scale_intensity(&self, world: &World, factor: f32) {
+        let target = self.target(world);
+        let mut lights = world.write_storage::<Light>();
+        let light = match target.and_then(|e| lights.get_mut(e)) {
+            Some(light) => light,
+            None => return,
+        };
+        let intensity = match light {
+            Light::Directional(directional) => &mut directional.intensity,
+            Light::Point(point) => &mut point.intensity,
+            Light::Spot(spot) => &mut spot.intensity,
+            Light::Sun(sun) => &mut sun.intensity,
+            Light::Area => return,
+        };
+        *intensity = (*intensity * factor).max(1.0e-3);
+        println!("{} light intensity {:.3}", light_kind(light), intensity_of(light));
+    }
+
+    /// Gives the selected light the next color of the palette.
+    pub fn cycle_color(&self, world: &World) {
+        let target = self.target(world);
+        let mut lights = world.write_storage::<Light>();
+        let light = match target.and_then(|e| lights.get_mut(e)) {
+            Some(light) => light,
+            None => return,
+        };
+        let color = match light {
+            Light::Directional(directional) => &mut directional.color,
+            Light::Point(point) => &mut point.color,
+            Light::Spot(spot) => &mut spot.color,
+            Light::Sun(sun) => &mut sun.color,
+            Light::Area => return,
+        };
+        let next = LIGHT_COLORS
+            .iter()
+            .position(|&c| c == rgb(*color))
+            .map_or(0, |index| (index + 1) % LIGHT_COLORS.len());
+        *color = srgb(LIGHT_COLORS[next]);
+    }
+}
+
+/// Adds a point light at `position` and selects it.
+pub fn add_point_light(world: &mut World, position: Point3<f32>) {
+    let light = RigPointLight {
+        position: position.coords.into(),
+        ..Default::default()
+    };
+    let entity = spawn_point_light(world, &light);
+    world.write_resource::<LightEditor>().selected = Some(entity);
+    println!("Added a point light");
+}
+
+/// Removes the selected light if it is a point light.
+pub fn remove_selected_light(world: &mut World) -> Result<(), Error> {
+    let entity = world
+        .read_resource::<LightEditor>()
+        .selected
+        .filter(|&e| matches!(world.read_storage::<Light>().get(e), Some(Light::Point(_))))
+        .ok_or_else(|| Error::from_string("select a point light to remove"))?;
+    world.delete_entity(entity)?;
+    world.write_resource::<LightEditor>().selected = None;
+    println!("Removed a point light");
+    Ok(())
+}
+
+fn key_light(entities: &Entities<'_>, lights: &ReadStorage<'_, Light>) -> Option<Entity> {
+    (&**entities, lights)
+        .join()
+        .find(|(_, light)| matches!(light, Light::Directional(_)))
+        .map(|(e, _)| e)
+}
+
+fn light_kind(light: &Light) -> &'static str {
+    match light {
+        Light::Area => "Area",
+        Light::Directional(_) => "Directional",
+        Light::Point(_) => "Point",
+        Light::Spot(_) => "Spot",
+        Light::Sun(_) => "Sun",
+    }
+}
+
+fn intensity_of(light: &Light) -> f32 {
+    match light {
+        Light::Directional(directional) => directional.intensity,
+        Light::Point(point) => point.intensity,
+        Light::Spot(spot) => spot.intensity,
+        Light::Sun(sun) => sun.intensity,
+        Light::Area => 0.0,
+    }
+}
+
+/// Turns the key light while the right mouse button is dragged, and draws light gizmos with
+/// `RenderDebugLines` while the `LightEditor` is enabled.
+#[derive(Debug)]
+pub struct LightEditorSystem<T: BindingTypes> {
+    marker: PhantomData<T>,
+}
+
+impl<T: BindingTypes> Default for LightEditorSystem<T> {
+    fn default() -> Self {
+        LightEditorSystem {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: BindingTypes> System<'a> for LightEditorSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, InputHandler<T>>,
+        Write<'a, LightEditor>,
+        Write<'a, DebugLines>,
+        WriteStorage<'a, Light>,
+        ReadStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, input, mut editor, mut debug_lines, mut lights, transforms): Self::SystemData,
+    ) {
+        if !editor.enabled {
+            editor.last_mouse = None;
+            return;
+        }
+
+        let mouse = input.mouse_position();
+        let dragging = input.mouse_button_is_down(MouseButton::Right);
+        let (dx, dy) = match (editor.last_mouse, mouse) {
+            (Some((x0, y0)), Some((x, y))) if dragging => (x - x0, y - y0),
+            _ => (0.0, 0.0),
+        };
+        editor.last_mouse = mouse;
+
+        let selected = editor
+            .selected
+            .filter(|&e| matches!(lights.get(e), Some(Light::Directional(_))));
+        let key = selected.or_else(|| {
+            (&*entities, &lights)
+                .join()
+                .find(|(_, light)| matches!(light, Light::Directional(_)))
+                .map(|(e, _)| e)
+        });
+        if let Some(Light::Directional(directional)) = key.and_then(|e| lights.get_mut(e)) {
+            if dx != 0.0 || dy != 0.0 {
+                let sensitivity = editor.rotate_sensitivity;
+                let yaw = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), -dx * sensitivity);
+                let across = Vector3::y()
+                    .cross(&directional.direction)
+                    .try_normalize(1.0e-6)
+                    .unwrap_or_else(Vector3::x);
+                let pitch = UnitQuaternion::from_scaled_axis(across * dy * sensitivity);
+                let turned = (yaw * pitch * directional.direction).normalize();
+                // Stop short of straight up or down, where dragging sideways would do nothing.
+                if turned.y.abs() < 0.99 {
+                    directional.direction = turned;
+                }
+            }
+        }
+
+        let size = editor.gizmo_size;
+        let highlighted = editor.selected.filter(|&e| lights.contains(e)).or(key);
+        for (entity, light) in (&*entities, &lights).join() {
+            let highlight = Some(entity) == highlighted;
+            match light {
+                Light::Directional(directional) => {
+                    let color = gizmo_color(directional.color, highlight);
+                    let direction = directional.direction.normalize() * size;
+                    let start = Point3::origin() - direction;
+                    debug_lines.draw_direction(start, direction, color);
+                    debug_lines.draw_sphere(start, size * 0.05, 6, 6, color);
+                }
+                Light::Point(point) => {
+                    let position = match transforms.get(entity) {
+                        Some(transform) => Point3::from(*transform.translation()),
+                        None => continue,
+                    };
+                    let color = gizmo_color(point.color, highlight);
+                    let scale = if highlight { 0.15 } else { 0.1 };
+                    debug_lines.draw_sphere(position, size * scale, 8, 8, color);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn gizmo_color(color: Srgb, highlight: bool) -> Srgba {
+    if highlight {
+        Srgba::new(1.0, 0.9, 0.2, 1.0)
+    } else {
+        Srgba::new(color.red, color.green, color.blue, 1.0)
+    }
+}
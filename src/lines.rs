@@ -0,0 +1,836 @@
+use amethyst::{
+    core::math::{Matrix4, Point3, Vector3, U3},
+    derive::PrefabData,
+    ecs::{Component, DenseVecStorage},
+    renderer::{rendy::mesh::Position, shape::Shape},
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::options::ModelFormat;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Positions closer than this are welded into a single vertex when building adjacency.
+const WELD_EPSILON: f32 = 1.0e-5;
+
+/// Which kind of feature an extracted edge belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// Edge between a front facing and a back facing triangle.
+    Silhouette,
+    /// Edge whose dihedral angle is sharper than the crease threshold.
+    Crease,
+    /// Edge used by a single triangle, i.e. the border of an open mesh.
+    Boundary,
+}
+
+/// Where the mesh is viewed from, used to classify front and back faces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Viewpoint {
+    /// Perspective camera located at the given world position.
+    Perspective(Point3<f32>),
+    /// Orthographic camera looking along the given world direction.
+    Orthographic(Vector3<f32>),
+}
+
+impl Viewpoint {
+    /// Builds a viewpoint from a camera's global matrix.
+    pub fn from_camera_matrix(global: &Matrix4<f32>, orthographic: bool) -> Self {
+        if orthographic {
+            // Amethyst cameras look down their local -Z axis.
+            Viewpoint::Orthographic(-global.column(2).xyz().normalize())
+        } else {
+            Viewpoint::Perspective(Point3::from(global.column(3).xyz()))
+        }
+    }
+
+    /// Expresses the viewpoint in another space, e.g. a model's local space given the
+    /// inverse of its global matrix, so edges can be extracted without transforming the mesh.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        match self {
+            Viewpoint::Perspective(eye) => Viewpoint::Perspective(matrix.transform_point(eye)),
+            Viewpoint::Orthographic(dir) => {
+                Viewpoint::Orthographic(matrix.transform_vector(dir).normalize())
+            }
+        }
+    }
+
+    /// Direction from `point` towards the viewer, not normalized.
+    pub fn to_viewer(&self, point: &Point3<f32>) -> Vector3<f32> {
+        match self {
+            Viewpoint::Perspective(eye) => eye - point,
+            Viewpoint::Orthographic(dir) => -dir,
+        }
+    }
+
+    fn is_front_facing(&self, normal: &Vector3<f32>, point: &Point3<f32>) -> bool {
+        match self {
+            Viewpoint::Perspective(eye) => normal.dot(&(eye - point)) > 0.0,
+            Viewpoint::Orthographic(dir) => normal.dot(dir) < 0.0,
+        }
+    }
+}
+
+/// Controls which features `LineMesh::extract` reports.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LineSettings {
+    /// Minimum angle in degrees between adjacent face normals for an edge to be a crease.
+    pub crease_angle: f32,
+    pub silhouettes: bool,
+    pub creases: bool,
+    pub boundaries: bool,
+    /// Skip creases and boundaries whose triangles all face away from the viewer.
+    pub cull_back_faces: bool,
+}
+
+impl Default for LineSettings {
+    fn default() -> Self {
+        LineSettings {
+            crease_angle: 40.0,
+            silhouettes: true,
+            creases: true,
+            boundaries: true,
+            cull_back_faces: true,
+        }
+    }
+}
+
+/// Marks an entity whose model should contribute feature lines, e.g. to the SVG exporter.
+///
+/// `model` is a glTF path relative to the assets directory; its geometry is read in bind pose
+/// and posed by the skins found among the entity's children.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct LineSource {
+    pub model: String,
+    pub settings: LineSettings,
+}
+
+impl Component for LineSource {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Lazily loaded `LineMesh`es keyed by their path relative to the assets directory.
+#[derive(Debug, Default)]
+pub struct LineMeshCache {
+    root: PathBuf,
+    meshes: HashMap<String, LineMesh>,
+}
+
+impl LineMeshCache {
+    pub fn new<P: Into<PathBuf>>(assets_dir: P) -> Self {
+        LineMeshCache {
+            root: assets_dir.into(),
+            meshes: HashMap::new(),
+        }
+    }
+
+    /// Directory model paths are relative to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the mesh for `model`, a glTF, GLB or OBJ file, loading it on first use.
+    pub fn get(&mut self, model: &str) -> Result<&LineMesh, Error> {
+        if !self.meshes.contains_key(model) {
+            let path = self.root.join(model);
+            let mesh = match ModelFormat::from_path(&path) {
+                Some(ModelFormat::Obj) => LineMesh::load_obj(path)?,
+                _ => LineMesh::load_gltf(path)?,
+            };
+            self.meshes.insert(model.to_string(), mesh);
+        }
+        Ok(&self.meshes[model])
+    }
+}
+
+/// A single extracted feature line segment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeatureEdge {
+    pub a: Point3<f32>,
+    pub b: Point3<f32>,
+    pub kind: EdgeKind,
+}
+
+#[derive(Clone, Debug)]
+struct MeshEdge {
+    vertices: [u32; 2],
+    faces: Vec<u32>,
+}
+
+/// Up to four joints of a glTF skin influencing one vertex.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JointWeights {
+    /// Index of the skin in `LineMesh::skins`.
+    pub skin: usize,
+    pub joints: [u16; 4],
+    pub weights: [f32; 4],
+}
+
+/// Triangle mesh with welded vertices and edge adjacency, ready for feature-line extraction.
+#[derive(Clone, Debug, Default)]
+pub struct LineMesh {
+    positions: Vec<Point3<f32>>,
+    triangles: Vec<[u32; 3]>,
+    normals: Vec<Vector3<f32>>,
+    edges: Vec<MeshEdge>,
+    // Per welded vertex, empty when the mesh is not skinned
+    weights: Vec<Option<JointWeights>>,
+    // Inverse bind matrices of every skin the weights refer to
+    skins: Vec<Vec<Matrix4<f32>>>,
+}
+
+impl LineMesh {
+    /// Builds a mesh from indexed triangles. Coincident positions are welded so that
+    /// meshes split along UV or normal seams still share their edges.
+    pub fn new(positions: &[[f32; 3]], indices: &[u32]) -> Self {
+        let (welded, remap) = Self::weld(positions);
+        Self::from_welded(welded, &remap, indices)
+    }
+
+    /// Builds a skinned mesh. `weights` holds the joint influences of each of `positions`,
+    /// `skins` the inverse bind matrices of the skins they refer to.
+    pub fn new_skinned(
+        positions: &[[f32; 3]],
+        indices: &[u32],
+        weights: &[Option<JointWeights>],
+        skins: Vec<Vec<Matrix4<f32>>>,
+    ) -> Self {
+        let (welded, remap) = Self::weld(positions);
+        // Welded vertices keep the influences of the first position they were made from.
+        let mut welded_weights = vec![None; welded.len()];
+        for (&index, weights) in remap.iter().zip(weights) {
+            let slot = &mut welded_weights[index as usize];
+            if slot.is_none() {
+                *slot = *weights;
+            }
+        }
+        let mut mesh = Self::from_welded(welded, &remap, indices);
+        mesh.weights = welded_weights;
+        mesh.skins = skins;
+        mesh
+    }
+
+    fn weld(positions: &[[f32; 3]]) -> (Vec<Point3<f32>>, Vec<u32>) {
+        let mut welded = Vec::new();
+        let mut lookup = HashMap::new();
+        let remap = positions
+            .iter()
+            .map(|p| {
+                let key = [
+                    (p[0] / WELD_EPSILON).round() as i64,
+                    (p[1] / WELD_EPSILON).round() as i64,
+                    (p[2] / WELD_EPSILON).round() as i64,
+                ];
+                *lookup.entry(key).or_insert_with(|| {
+                    welded.push(Point3::new(p[0], p[1], p[2]));
+                    welded.len() as u32 - 1
+                })
+            })
+            .collect::<Vec<u32>>();
+        (welded, remap)
+    }
+
+    fn from_welded(welded: Vec<Point3<f32>>, remap: &[u32], indices: &[u32]) -> Self {
+        let mut triangles = Vec::with_capacity(indices.len() / 3);
+        let mut normals = Vec::with_capacity(indices.len() / 3);
+        for tri in indices.chunks_exact(3) {
+            let t = [
+                remap[tri[0] as usize],
+                remap[tri[1] as usize],
+                remap[tri[2] as usize],
+            ];
+            let (p0, p1, p2) = (
+                &welded[t[0] as usize],
+                &welded[t[1] as usize],
+                &welded[t[2] as usize],
+            );
+            let normal = (p1 - p0).cross(&(p2 - p0));
+            // Degenerate triangles (such as those at the poles of a UV sphere) have no
+            // meaningful normal and would only produce spurious features.
+            if normal.norm_squared() <= std::f32::EPSILON {
+                continue;
+            }
+            triangles.push(t);
+            normals.push(normal.normalize());
+        }
+
+        let mut edge_lookup: HashMap<(u32, u32), usize> = HashMap::new();
+        let mut edges: Vec<MeshEdge> = Vec::new();
+        for (face, t) in triangles.iter().enumerate() {
+            for &(i, j) in &[(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                let key = (i.min(j), i.max(j));
+                let edge = *edge_lookup.entry(key).or_insert_with(|| {
+                    edges.push(MeshEdge {
+                        vertices: [key.0, key.1],
+                        faces: Vec::with_capacity(2),
+                    });
+                    edges.len() - 1
+                });
+                edges[edge].faces.push(face as u32);
+            }
+        }
+
+        LineMesh {
+            positions: welded,
+            triangles,
+            normals,
+            edges,
+            ..Default::default()
+        }
+    }
+
+    /// Builds a mesh from a non-indexed triangle list, as produced by `Shape`.
+    pub fn from_triangle_list(positions: &[[f32; 3]]) -> Self {
+        let indices = (0..positions.len() as u32).collect::<Vec<_>>();
+        Self::new(positions, &indices)
+    }
+
+    /// Builds a mesh from one of Amethyst's procedural shapes.
+    pub fn from_shape(shape: &Shape, scale: Option<(f32, f32, f32)>) -> Self {
+        let positions = shape
+            .generate_vertices::<Vec<Position>>(scale)
+            .into_iter()
+            .map(|p| p.0)
+            .collect::<Vec<_>>();
+        Self::from_triangle_list(&positions)
+    }
+
+    /// Loads every triangle primitive of a glTF file's default scene, baking node transforms.
+    ///
+    /// Skinned meshes are read in their bind pose, matching what `GltfSceneFormat` uploads,
+    /// along with their joint weights so they can be `posed`.
+    pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let (document, buffers, _) = gltf::import(path).map_err(Error::new)?;
+        let scene = match document.default_scene().or_else(|| document.scenes().next()) {
+            Some(scene) => scene,
+            None => return Ok(LineMesh::default()),
+        };
+
+        let skins = document
+            .skins()
+            .map(|skin| {
+                let reader = skin.reader(|buffer| Some(&buffers[buffer.index()].0[..]));
+                match reader.read_inverse_bind_matrices() {
+                    Some(read) => read.map(Matrix4::from).collect(),
+                    None => vec![Matrix4::identity(); skin.joints().count()],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        let mut weights = Vec::new();
+        let mut stack = scene
+            .nodes()
+            .map(|node| (node, Matrix4::<f32>::identity()))
+            .collect::<Vec<_>>();
+        while let Some((node, parent)) = stack.pop() {
+            let global = parent * Matrix4::from(node.transform().matrix());
+            if let Some(mesh) = node.mesh() {
+                // The glTF spec ignores the node transform of skinned meshes.
+                let transform = if node.skin().is_some() {
+                    Matrix4::identity()
+                } else {
+                    global
+                };
+                for primitive in mesh.primitives() {
+                    if primitive.mode() != gltf::mesh::Mode::Triangles {
+                        continue;
+                    }
+                    let reader =
+                        primitive.reader(|buffer| Some(&buffers[buffer.index()].0[..]));
+                    let base = positions.len() as u32;
+                    let count = match reader.read_positions() {
+                        Some(read) => {
+                            let before = positions.len();
+                            positions.extend(read.map(|p| {
+                                let p = transform.transform_point(&Point3::from(p));
+                                [p.x, p.y, p.z]
+                            }));
+                            positions.len() - before
+                        }
+                        None => continue,
+                    };
+                    match reader.read_indices() {
+                        Some(read) => indices.extend(read.into_u32().map(|i| base + i)),
+                        None => indices.extend(base..base + count as u32),
+                    }
+                    let skin = node.skin().map(|skin| skin.index());
+                    if let (Some(skin), Some(joints), Some(read)) =
+                        (skin, reader.read_joints(0), reader.read_weights(0))
+                    {
+                        weights.extend(joints.into_u16().zip(read.into_f32()).map(
+                            |(joints, weights)| {
+                                Some(JointWeights {
+                                    skin,
+                                    joints,
+                                    weights,
+                                })
+                            },
+                        ));
+                    }
+                    // Unskinned primitives, or ones with too few weights, stay in bind pose.
+                    weights.resize(positions.len(), None);
+                }
+            }
+            stack.extend(node.children().map(|child| (child, global)));
+        }
+        Ok(Self::new_skinned(&positions, &indices, &weights, skins))
+    }
+
+    /// Loads the faces of a Wavefront OBJ file, triangulating polygons as fans.
+    pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)?;
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("v") => {
+                    let mut coord = || words.next().and_then(|w| w.parse().ok()).unwrap_or(0.0);
+                    positions.push([coord(), coord(), coord()]);
+                }
+                Some("f") => {
+                    // Each corner is `v`, `v/vt`, `v//vn` or `v/vt/vn`, 1-based or negative.
+                    let count = positions.len() as i64;
+                    let face = words
+                        .filter_map(|corner| corner.split('/').next()?.parse::<i64>().ok())
+                        .map(|i| if i < 0 { count + i } else { i - 1 })
+                        .filter(|&i| i >= 0 && i < count)
+                        .map(|i| i as u32)
+                        .collect::<Vec<_>>();
+                    for i in 1..face.len().saturating_sub(1) {
+                        indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Self::new(&positions, &indices))
+    }
+
+    /// Returns a copy of this mesh with every position transformed by `matrix`.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let normal_matrix = matrix
+            .fixed_slice::<U3, U3>(0, 0)
+            .try_inverse()
+            .map(|m| m.transpose())
+            .unwrap_or_else(|| matrix.fixed_slice::<U3, U3>(0, 0).into_owned());
+        LineMesh {
+            positions: self
+                .positions
+                .iter()
+                .map(|p| matrix.transform_point(p))
+                .collect(),
+            triangles: self.triangles.clone(),
+            normals: self
+                .normals
+                .iter()
+                .map(|n| (normal_matrix * n).normalize())
+                .collect(),
+            edges: self.edges.clone(),
+            weights: self.weights.clone(),
+            skins: self.skins.clone(),
+        }
+    }
+
+    /// Returns a world space copy of this mesh in the pose given by `joints`.
+    ///
+    /// `joints` has an entry per skin in `skins`, holding the global matrix of each joint
+    /// times its inverse bind matrix, or `None` to leave that skin in its bind pose.
+    /// Vertices left in their bind pose are transformed by `global`, the model's global matrix.
+    pub fn posed(&self, global: &Matrix4<f32>, joints: &[Option<Vec<Matrix4<f32>>>]) -> Self {
+        let positions = self
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let weights = self.weights.get(i).copied().flatten();
+                let matrices = weights.and_then(|w| joints.get(w.skin)?.as_ref());
+                match (weights, matrices) {
+                    (Some(weights), Some(matrices)) => {
+                        let mut skinned = Vector3::zeros();
+                        for (&joint, &weight) in weights.joints.iter().zip(&weights.weights) {
+                            if let Some(matrix) = matrices.get(joint as usize) {
+                                skinned += matrix.transform_point(p).coords * weight;
+                            }
+                        }
+                        Point3::from(skinned)
+                    }
+                    _ => global.transform_point(p),
+                }
+            })
+            .collect::<Vec<_>>();
+        // Skinning bends triangles differently, so normals are rebuilt rather than transformed.
+        let normals = self
+            .triangles
+            .iter()
+            .map(|t| {
+                let (p0, p1, p2) = (
+                    &positions[t[0] as usize],
+                    &positions[t[1] as usize],
+                    &positions[t[2] as usize],
+                );
+                (p1 - p0)
+                    .cross(&(p2 - p0))
+                    .try_normalize(std::f32::EPSILON)
+                    .unwrap_or_else(Vector3::zeros)
+            })
+            .collect();
+        LineMesh {
+            positions,
+            triangles: self.triangles.clone(),
+            normals,
+            edges: self.edges.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Inverse bind matrices of the skins deforming this mesh, in glTF order.
+    pub fn skins(&self) -> &[Vec<Matrix4<f32>>] {
+        &self.skins
+    }
+
+    /// Welded vertex positions.
+    pub fn positions(&self) -> &[Point3<f32>] {
+        &self.positions
+    }
+
+    /// Non-degenerate triangles, indexing into `positions`.
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    /// Number of unique edges in the mesh.
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Classifies every edge of the mesh as seen from `viewpoint`.
+    ///
+    /// An edge matching several features is reported once, with boundaries taking
+    /// precedence over silhouettes and silhouettes over creases.
+    pub fn extract(&self, viewpoint: &Viewpoint, settings: &LineSettings) -> Vec<FeatureEdge> {
+        let facing = self
+            .triangles
+            .iter()
+            .zip(&self.normals)
+            .map(|(t, n)| viewpoint.is_front_facing(n, &self.positions[t[0] as usize]))
+            .collect::<Vec<_>>();
+        let crease_cos = settings.crease_angle.to_radians().cos();
+
+        self.edges
+            .iter()
+            .filter_map(|edge| {
+                let any_front = edge.faces.iter().any(|&f| facing[f as usize]);
+                let kind = match edge.faces.as_slice() {
+                    [_] if settings.boundaries => Some(EdgeKind::Boundary),
+                    [f0, f1] => {
+                        let (f0, f1) = (*f0 as usize, *f1 as usize);
+                        if settings.silhouettes && facing[f0] != facing[f1] {
+                            Some(EdgeKind::Silhouette)
+                        } else if settings.creases
+                            && self.normals[f0].dot(&self.normals[f1]) < crease_cos
+                        {
+                            Some(EdgeKind::Crease)
+                        } else {
+                            None
+                        }
+                    }
+                    // Non-manifold edges are always sharp features.
+                    faces if faces.len() > 2 && settings.creases => Some(EdgeKind::Crease),
+                    _ => None,
+                }?;
+                if kind != EdgeKind::Silhouette && settings.cull_back_faces && !any_front {
+                    return None;
+                }
+                Some(FeatureEdge {
+                    a: self.positions[edge.vertices[0] as usize],
+                    b: self.positions[edge.vertices[1] as usize],
+                    kind,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A chain of connected feature edges sharing a single kind.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureChain {
+    pub kind: EdgeKind,
+    pub points: Vec<Point3<f32>>,
+    /// Whether the chain returns to its first point, e.g. a full silhouette loop.
+    pub closed: bool,
+}
+
+/// Joins extracted edges that share endpoints and kind into the longest chains possible.
+pub fn chain_edges(edges: &[FeatureEdge]) -> Vec<FeatureChain> {
+    let key = |p: &Point3<f32>| {
+        [
+            (p.x / WELD_EPSILON).round() as i64,
+            (p.y / WELD_EPSILON).round() as i64,
+            (p.z / WELD_EPSILON).round() as i64,
+        ]
+    };
+
+    let mut endpoints: HashMap<(EdgeKind, [i64; 3]), Vec<usize>> = HashMap::new();
+    for (i, edge) in edges.iter().enumerate() {
+        endpoints.entry((edge.kind, key(&edge.a))).or_default().push(i);
+        endpoints.entry((edge.kind, key(&edge.b))).or_default().push(i);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let next_from = |point: &Point3<f32>, kind: EdgeKind, used: &mut Vec<bool>| {
+        let next = *endpoints
+            .get(&(kind, key(point)))?
+            .iter()
+            .find(|&&i| !used[i])?;
+        used[next] = true;
+        let edge = &edges[next];
+        Some(if key(&edge.a) == key(point) {
+            edge.b
+        } else {
+            edge.a
+        })
+    };
+
+    let mut chains = Vec::new();
+    for i in 0..edges.len() {
+        if used[i] {
+            continue;
+        }
+        used[i] = true;
+        let kind = edges[i].kind;
+        let mut points = vec![edges[i].a, edges[i].b];
+        while let Some(point) = next_from(points.last().unwrap(), kind, &mut used) {
+            points.push(point);
+        }
+        points.reverse();
+        while let Some(point) = next_from(points.last().unwrap(), kind, &mut used) {
+            points.push(point);
+        }
+        let closed = points.len() > 2 && key(&points[0]) == key(points.last().unwrap());
+        chains.push(FeatureChain {
+            kind,
+            points,
+            closed,
+        });
+    }
+    chains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_FEATURES: LineSettings = LineSettings {
+        crease_angle: 40.0,
+        silhouettes: true,
+        creases: true,
+        boundaries: true,
+        cull_back_faces: false,
+    };
+
+    fn count(edges: &[FeatureEdge], kind: EdgeKind) -> usize {
+        edges.iter().filter(|e| e.kind == kind).count()
+    }
+
+    fn quad() -> LineMesh {
+        LineMesh::new(
+            &[
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        )
+    }
+
+    #[test]
+    fn welds_coincident_positions() {
+        let mesh = LineMesh::from_triangle_list(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0e-7],
+            [0.0, 1.0, 0.0],
+        ]);
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(mesh.triangles().len(), 2);
+        assert_eq!(mesh.edge_count(), 5);
+    }
+
+    #[test]
+    fn drops_degenerate_triangles() {
+        let mesh = LineMesh::new(
+            &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]],
+            &[0, 1, 2],
+        );
+        assert!(mesh.triangles().is_empty());
+        assert_eq!(mesh.edge_count(), 0);
+    }
+
+    #[test]
+    fn cube_welds_to_eight_corners() {
+        let cube = LineMesh::from_shape(&Shape::Cube, None);
+        assert_eq!(cube.positions().len(), 8);
+        assert_eq!(cube.triangles().len(), 12);
+        // 12 cube edges plus a diagonal per face.
+        assert_eq!(cube.edge_count(), 18);
+    }
+
+    #[test]
+    fn cube_has_twelve_creases() {
+        let cube = LineMesh::from_shape(&Shape::Cube, None);
+        let settings = LineSettings {
+            silhouettes: false,
+            ..ALL_FEATURES
+        };
+        let edges = cube.extract(
+            &Viewpoint::Perspective(Point3::new(0.0, 0.0, 10.0)),
+            &settings,
+        );
+        assert_eq!(count(&edges, EdgeKind::Crease), 12);
+        assert_eq!(edges.len(), 12);
+        for edge in &edges {
+            // Cube edges run along one axis, face diagonals would not.
+            let d = edge.b - edge.a;
+            let axes = d.iter().filter(|c| c.abs() > 1.0e-4).count();
+            assert_eq!(axes, 1, "{:?} is not a cube edge", edge);
+        }
+    }
+
+    #[test]
+    fn cube_silhouette_from_the_front() {
+        // Only the +Z face is visible from straight ahead, so its border is the silhouette
+        // and the creases between back faces are culled.
+        let cube = LineMesh::from_shape(&Shape::Cube, None);
+        let edges = cube.extract(
+            &Viewpoint::Perspective(Point3::new(0.0, 0.0, 10.0)),
+            &LineSettings::default(),
+        );
+        assert_eq!(count(&edges, EdgeKind::Silhouette), 4);
+        assert_eq!(edges.len(), 4);
+        for edge in &edges {
+            assert!((edge.a.z - 1.0).abs() < 1.0e-4 && (edge.b.z - 1.0).abs() < 1.0e-4);
+        }
+    }
+
+    #[test]
+    fn cube_silhouette_orthographic_corner() {
+        // Looking down the main diagonal three faces are visible, and the silhouette is
+        // the hexagon around them.
+        let cube = LineMesh::from_shape(&Shape::Cube, None);
+        let settings = LineSettings {
+            creases: false,
+            ..ALL_FEATURES
+        };
+        let viewpoint = Viewpoint::Orthographic(Vector3::new(-1.0, -1.0, -1.0));
+        let edges = cube.extract(&viewpoint, &settings);
+        assert_eq!(count(&edges, EdgeKind::Silhouette), 6);
+        assert_eq!(chain_edges(&edges).len(), 1);
+        assert!(chain_edges(&edges)[0].closed);
+    }
+
+    #[test]
+    fn sphere_has_no_creases_below_threshold() {
+        let sphere = LineMesh::from_shape(&Shape::Sphere(32, 32), None);
+        let settings = LineSettings {
+            silhouettes: false,
+            boundaries: false,
+            ..ALL_FEATURES
+        };
+        let edges = sphere.extract(
+            &Viewpoint::Perspective(Point3::new(0.0, 0.0, 10.0)),
+            &settings,
+        );
+        assert_eq!(count(&edges, EdgeKind::Crease), 0);
+    }
+
+    #[test]
+    fn sphere_silhouette_is_a_closed_loop() {
+        let sphere = LineMesh::from_shape(&Shape::Sphere(32, 32), None);
+        let settings = LineSettings {
+            creases: false,
+            boundaries: false,
+            ..ALL_FEATURES
+        };
+        let viewpoint = Viewpoint::Orthographic(Vector3::new(0.0, 0.0, -1.0));
+        let edges = sphere.extract(&viewpoint, &settings);
+        assert!(!edges.is_empty());
+        assert!(edges.iter().all(|e| e.kind == EdgeKind::Silhouette));
+        let chains = chain_edges(&edges);
+        assert!(chains.iter().all(|c| c.closed));
+    }
+
+    #[test]
+    fn open_quad_has_four_boundaries() {
+        let edges = quad().extract(
+            &Viewpoint::Perspective(Point3::new(0.5, 0.5, 5.0)),
+            &ALL_FEATURES,
+        );
+        assert_eq!(count(&edges, EdgeKind::Boundary), 4);
+        assert_eq!(edges.len(), 4);
+        let chains = chain_edges(&edges);
+        assert_eq!(chains.len(), 1);
+        assert!(chains[0].closed);
+        assert_eq!(chains[0].points.len(), 5);
+    }
+
+    #[test]
+    fn back_facing_quad_boundaries_are_culled() {
+        let viewpoint = Viewpoint::Perspective(Point3::new(0.5, 0.5, -5.0));
+        assert!(quad()
+            .extract(&viewpoint, &LineSettings::default())
+            .is_empty());
+        assert_eq!(quad().extract(&viewpoint, &ALL_FEATURES).len(), 4);
+    }
+
+    #[test]
+    fn skinned_quad_follows_its_joints() {
+        // The bottom edge follows joint 0 and the top edge joint 1, with the same weld
+        // duplicated to check that welded vertices keep their influences.
+        let weight = |joint| {
+            Some(JointWeights {
+                skin: 0,
+                joints: [joint, 0, 0, 0],
+                weights: [1.0, 0.0, 0.0, 0.0],
+            })
+        };
+        let mesh = LineMesh::new_skinned(
+            &[
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 1.0, 0.0],
+            ],
+            &[0, 1, 2, 0, 4, 3],
+            &[weight(0), weight(0), weight(1), weight(1), weight(0)],
+            vec![vec![Matrix4::identity(); 2]],
+        );
+        assert_eq!(mesh.positions().len(), 4);
+
+        let global = Matrix4::new_translation(&Vector3::new(10.0, 0.0, 0.0));
+        let lifted = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 1.0));
+        let posed = mesh.posed(&global, &[Some(vec![Matrix4::identity(), lifted])]);
+        assert_eq!(posed.positions()[1], Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(posed.positions()[2], Point3::new(1.0, 1.0, 1.0));
+        // Normals are rebuilt for the tilted quad.
+        let normal = posed.normals[0];
+        assert!((normal - Vector3::new(0.0, -1.0, 1.0).normalize()).norm() < 1.0e-5);
+
+        // Without joint matrices the skin stays in its bind pose, placed by `global`.
+        let bind = mesh.posed(&global, &[None]);
+        assert_eq!(bind.positions()[2], Point3::new(11.0, 1.0, 0.0));
+    }
+}
@@ -1,30 +1,73 @@
 // use npr_app::custom_render::RenderCustom;
 
-use amethyst::{Application, Error, GameData, GameDataBuilder, SimpleState, StateData, animation::*, assets::{
+use amethyst::{Application, Error, GameData, GameDataBuilder, SimpleState, StateData, animation::*, config::Config, assets::{
         AssetLoaderSystemData, AssetPrefab, AssetStorage, Completion, Handle, Loader, Prefab,
-        PrefabData, PrefabLoader, PrefabLoaderSystemDesc, ProgressCounter, RonFormat,
-    }, controls::{ControlTagPrefab, FlyControlBundle, FlyControlTag}, core::{Transform, TransformBundle}, derive::PrefabData, ecs::{
-        prelude::{Entity, World, WorldExt},
-        ReadStorage, Write, WriteStorage,
-    }, input::{get_key, is_close_requested, is_key_down, InputBundle, StringBindings}, prelude::*, renderer::{Camera, ImageFormat, Material, MaterialDefaults, Mesh, RenderDebugLines, RenderShaded3D, RenderSkybox, RenderingBundle, camera::CameraPrefab, formats::mesh::ObjFormat, light::{Light, LightPrefab, PointLight}, palette::rgb::Rgb, plugins::{RenderPbr3D, RenderToWindow}, rendy::mesh::{Normal, Position, Tangent, TexCoord}, shape::Shape, types::DefaultBackend}, utils::{
+        HotReloadBundle, HotReloadStrategy, PrefabData, PrefabLoader, PrefabLoaderSystemDesc,
+        Processor, ProgressCounter, RonFormat,
+    }, controls::{ControlTagPrefab, FlyControlBundle, FlyControlTag, HideCursor}, core::{math::{Matrix4, Point3}, Parent, ParentHierarchy, Transform, TransformBundle}, derive::PrefabData, ecs::{
+        prelude::{Entities, Entity, World, WorldExt},
+        Join, ReadStorage, Write, WriteStorage,
+    }, input::{is_close_requested, InputBundle, InputEvent, InputHandler, StringBindings}, prelude::*, renderer::{Camera, ImageFormat, debug_drawing::{DebugLines, DebugLinesParams}, camera::Projection, Material, MaterialDefaults, Mesh, RenderDebugLines, RenderShaded3D, RenderSkybox, RenderingBundle, bundle::Target, camera::CameraPrefab, light::{Light, LightPrefab, PointLight}, palette::rgb::Rgb, plugins::{RenderPbr3D, RenderToWindow}, rendy::mesh::{Normal, Position, Tangent, TexCoord}, shape::Shape, types::DefaultBackend}, utils::{
         application_root_dir,
         auto_fov::{AutoFov, AutoFovSystem},
+        fps_counter::FpsCounterBundle,
         scene::BasicScenePrefab,
-        tag::{Tag, TagFinder},
-    }, window::DisplayConfig, winit::{ElementState, VirtualKeyCode}};
+        tag::Tag,
+    }, ui::{RenderUi, UiBundle}, window::{DisplayConfig, ScreenDimensions}, winit::{ElementState, VirtualKeyCode}};
 use amethyst_gltf::*;
 use serde::{Deserialize, Serialize};
-use npr_app::custom_render::RenderCustom3D;
+use npr_app::{
+    animation::{
+        apply_playback, AnimationClips, AnimationStep, AnimationStepSystem, CrossFade,
+        CrossFadeSystem, ManualClock, ManualClockSystem, PlaybackCommand,
+    },
+    antialiasing::{Antialiasing, RenderAntialiasing, SCENE_TARGET},
+    boil::{LineBoil, LineBoilSystem},
+    camera::{
+        frame_bounds, toggle_projection, Bounds, CameraBookmark, CameraBookmarks, CameraPath,
+        CameraPathPlayer, CameraPathSystem, OrbitCamera, OrbitCameraSystem, OrthographicView,
+        ViewPreset,
+    },
+    browser::ModelBrowser,
+    custom_render::RenderCustom3D,
+    debug_view::DebugView,
+    hud::{Hud, HudSystem},
+    style::{BandSoftness, PostEffects, StylePreset, StylePresetSystem, StylePresets},
+    lighting::{
+        add_point_light, remove_selected_light, LightEditor, LightEditorSystem, LightRig,
+    },
+    lines::{LineMesh, LineMeshCache, LineSettings, LineSource},
+    obj::{AssetsDir, ObjPrefab},
+    onion::{OnionSkin, OnionSkinSystem},
+    options::{ModelFormat, Options, SceneSource, ShadingStyle, USAGE},
+    settings::{load_display_config, RenderSettings},
+    squash::SquashStretch,
+    strokes::{joint_matrices, RenderStrokes},
+    svg::{render_line_drawing, SvgStyle},
+};
 
-const CLEAR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
-const WIN_WIDTH: f32 = 1024.0;
-const WIN_HEIGHT: f32 = 768.0;
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+const DEFAULT_CAMERA_PATH: &str = "camera/turntable.ron";
 
 #[derive(Default, Deserialize, Serialize, PrefabData)]
 #[serde(default)]
 struct AnimationPrefabData {
     gltf: Option<AssetPrefab<GltfSceneAsset, GltfSceneFormat>>,
+    obj: Option<ObjPrefab>,
+    transform: Option<Transform>,
     tag: Option<Tag<AnimationMarker>>,
+    lines: Option<LineSource>,
+    step: Option<AnimationStep>,
+    clips: Option<AnimationClips>,
+    crossfade: Option<CrossFade>,
+    squash: Option<SquashStretch>,
+    band_softness: Option<BandSoftness>,
 }
 
 #[derive(Default, Deserialize, Serialize, PrefabData)]
@@ -40,31 +83,92 @@ struct AnimationMarker;
 #[derive(Default)]
 struct Scene {
     anim_handle: Option<Handle<Prefab<AnimationPrefabData>>>,
-    animation_index: usize,
+    // Clip currently running on each tagged model
+    animations: HashMap<Entity, usize>,
 }
 #[derive(Default)]
 struct AniObject {
+    // Every model tagged with `AnimationMarker`, in prefab order
+    models: Vec<Entity>,
+    // Model targeted by the animation controls
     entity: Option<Entity>,
+    // Entity the model prefab was instantiated on
+    prefab_entity: Option<Entity>,
     initialized: bool,
     progress: Option<ProgressCounter>,
+    assets_dir: PathBuf,
+    scene: SceneSource,
+    browser: ModelBrowser,
+    // Export the line drawing to this path once loaded, then quit
+    svg_export: Option<PathBuf>,
+    // Set when the `svg_export` failed, so `main` can exit with an error status
+    export_failed: Rc<Cell<bool>>,
+    // Clip to start once loaded, by name or index
+    start_animation: Option<String>,
+    // Pose the starting clip at this time instead of playing it
+    start_time: Option<f32>,
+    // Camera path toggled by `play_camera_path`, relative to the assets directory
+    camera_path: String,
+    // Play `camera_path` as soon as the models are loaded
+    start_camera_path: bool,
+    bookmarks_path: PathBuf,
+    // Saved lighting rig, used instead of the lights in `prefabs/scene.ron` when present
+    lighting_path: PathBuf,
+    style: ShadingStyle,
+    // Style preset to start with, from `--preset`
+    preset: Option<String>,
 }
 
 impl SimpleState for AniObject {
     fn on_start(&mut self, state_data: StateData<'_, GameData<'_, '_>>) {
         let StateData { world, .. } = state_data;
         initialize_camera(world);
+        Hud::create(world);
 
-        self.progress = Some(ProgressCounter::default());
-
-        world.exec(
-            |(loader, mut scene): (PrefabLoader<'_, AnimationPrefabData>, Write<'_, Scene>)| {
-                scene.anim_handle = Some(loader.load(
-                    "prefabs/model_animation.ron",
-                    RonFormat,
-                    self.progress.as_mut().unwrap(),
-                ));
-            },
+        let presets = StylePresets::load(
+            &self.assets_dir,
+            &world.read_resource::<Loader>(),
+            &world.read_resource::<AssetStorage<StylePreset>>(),
         );
+        match presets {
+            Ok(mut presets) => {
+                if let Some(name) = &self.preset {
+                    presets.select_name(name);
+                }
+                world.insert(presets);
+            }
+            Err(e) => eprintln!("Could not read the style presets: {}", e),
+        }
+
+        // A missing bookmarks file just means nothing has been saved yet.
+        let bookmarks = if self.bookmarks_path.is_file() {
+            CameraBookmarks::load(&self.bookmarks_path).unwrap_or_else(|e| {
+                eprintln!("Could not read camera bookmarks: {}", e);
+                CameraBookmarks::default()
+            })
+        } else {
+            CameraBookmarks::default()
+        };
+        world.insert(bookmarks);
+
+        match ModelBrowser::scan(&self.assets_dir) {
+            Ok(browser) => self.browser = browser,
+            Err(e) => eprintln!("Could not scan {} for models: {}", self.assets_dir.display(), e),
+        }
+        if let SceneSource::Model { path, .. } = &self.scene {
+            self.browser.select(path);
+        }
+
+        self.load_models(world);
+        if self.lighting_path.is_file() {
+            match LightRig::load(&self.lighting_path) {
+                Ok(rig) => {
+                    rig.spawn(world);
+                    return;
+                }
+                Err(e) => eprintln!("Could not read the lighting rig: {}", e),
+            }
+        }
         let scene_handle = world.exec(|loader: PrefabLoader<'_, ScenePrefabData>| {
             loader.load("prefabs/scene.ron", RonFormat, ())
         });
@@ -85,29 +189,72 @@ impl SimpleState for AniObject {
                         .unwrap()
                         .clone();
 
-                    state_data.world.create_entity().with(scene_handle).build();
+                    self.prefab_entity =
+                        Some(state_data.world.create_entity().with(scene_handle).build());
 
                     true
                 }
 
                 Some(Completion::Failed) => {
-                    println!("Error: {:?}", self.progress.as_ref().unwrap().errors());
+                    for error in self.progress.as_ref().unwrap().errors() {
+                        eprintln!(
+                            "Failed to load {} `{}`: {}",
+                            error.asset_type_name, error.asset_name, error.error
+                        );
+                    }
                     return Trans::Quit;
                 }
             };
             if remove {
                 self.progress = None;
             }
-            if self.entity.is_none() {
-                if let Some(entity) = state_data
-                    .world
-                    .exec(|finder: TagFinder<'_, AnimationMarker>| finder.find())
-                {
-                    self.entity = Some(entity);
+            if self.models.is_empty() {
+                self.models = state_data.world.exec(
+                    |(entities, tags): (Entities<'_>, ReadStorage<'_, Tag<AnimationMarker>>)| {
+                        (&*entities, &tags).join().map(|(entity, _)| entity).collect()
+                    },
+                );
+                if let Some(&entity) = self.models.first() {
                     self.initialized = true;
+                    for &model in &self.models {
+                        if let Some(clips) = state_data
+                            .world
+                            .write_storage::<AnimationClips>()
+                            .get_mut(model)
+                        {
+                            if let Err(e) = clips.load_names(&self.assets_dir) {
+                                eprintln!("Could not read animation names: {}", e);
+                            }
+                        }
+                    }
+                    self.select(state_data.world, entity);
+                    self.start_requested_animation(state_data.world, entity);
+                    if let Err(e) = frame_camera(state_data.world) {
+                        eprintln!("Could not frame the camera: {}", e);
+                    }
+                    if std::mem::take(&mut self.start_camera_path) {
+                        let path = self.assets_dir.join(&self.camera_path);
+                        if let Err(e) = toggle_camera_path(state_data.world, &path) {
+                            eprintln!("Could not play camera path `{}`: {}", self.camera_path, e);
+                        }
+                    }
+                }
+            }
+            if self.initialized && self.is_posed(state_data.world) {
+                if let Some(path) = self.svg_export.take() {
+                    if let Err(e) = export_svg(state_data.world, &path) {
+                        eprintln!(
+                            "Could not export the line drawing to {}: {}",
+                            path.display(),
+                            e
+                        );
+                        self.export_failed.set(true);
+                    }
+                    return Trans::Quit;
                 }
             }
         }
+        self.update_hud(state_data.world);
         Trans::None
     }
 
@@ -118,95 +265,813 @@ impl SimpleState for AniObject {
     ) -> SimpleTrans {
         let StateData { world, .. } = state_data;
         if let StateEvent::Window(event) = &event {
-            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+            if is_close_requested(&event) {
                 return Trans::Quit;
-            } else if is_key_down(&event, VirtualKeyCode::Space) {
-                toggle_or_cycle_animation(
-                    self.entity,
-                    &mut world.write_resource(),
-                    &world.read_storage(),
-                    &mut world.write_storage(),
-                );
-                Trans::None
-            } else {
-                Trans::None
             }
-        } else {
-            Trans::None
+        } else if let StateEvent::Input(InputEvent::ActionPressed(action)) = &event {
+            if let Some(command) = PlaybackCommand::from_action(action) {
+                control_animation(world, self.entity, command);
+                return Trans::None;
+            }
+            match action.as_str() {
+                "quit" => return Trans::Quit,
+                "model_next" | "model_previous" => {
+                    let path = if action == "model_next" {
+                        self.browser.next_model()
+                    } else {
+                        self.browser.previous_model()
+                    }
+                    .map(str::to_string);
+                    if let Some(path) = path {
+                        println!("Loading model {}", path);
+                        let format = ModelFormat::from_path(Path::new(&path));
+                        self.unload_models(world);
+                        self.scene = SceneSource::Model {
+                            path,
+                            format: format.unwrap_or(ModelFormat::Gltf),
+                        };
+                        self.load_models(world);
+                    }
+                }
+                "model_list" => {
+                    let mut hud = world.write_resource::<Hud>();
+                    hud.show_models = !hud.show_models;
+                }
+                "toggle_orbit" => toggle_orbit_camera(world, false),
+                "toggle_turntable" => toggle_orbit_camera(world, true),
+                "frame_camera" => {
+                    if let Err(e) = frame_camera(world) {
+                        eprintln!("Could not frame the camera: {}", e);
+                    }
+                }
+                "toggle_projection" => toggle_projection_mode(world),
+                "view_isometric" => apply_view_preset(world, ViewPreset::Isometric),
+                "view_dimetric" => apply_view_preset(world, ViewPreset::Dimetric),
+                "toggle_hud" => {
+                    let mut hud = world.write_resource::<Hud>();
+                    hud.visible = !hud.visible;
+                }
+                "hud_next_param" => world.write_resource::<Hud>().select_next(),
+                "hud_previous_param" => world.write_resource::<Hud>().select_previous(),
+                "hud_increase" | "hud_decrease" => {
+                    let param = world.read_resource::<Hud>().selected_param();
+                    param.adjust(world, if action == "hud_increase" { 1 } else { -1 });
+                }
+                "toggle_fxaa" => {
+                    let mut post_effects = world.write_resource::<PostEffects>();
+                    let mut antialiasing = world.write_resource::<Antialiasing>();
+                    antialiasing.toggle_fxaa(&mut post_effects);
+                    println!("Anti-aliasing: {}", antialiasing.name(&post_effects));
+                }
+                "cycle_debug_view" => {
+                    let mut view = world.entry::<DebugView>().or_insert_with(Default::default);
+                    *view = view.next();
+                    println!("Debug view: {}", view.name());
+                }
+                "toggle_light_editor" => {
+                    let mut editor = world.write_resource::<LightEditor>();
+                    editor.enabled = !editor.enabled;
+                    println!("Light editor {}", if editor.enabled { "on" } else { "off" });
+                }
+                "light_select" => world.write_resource::<LightEditor>().select_next(world),
+                "light_brighter" => {
+                    world.read_resource::<LightEditor>().scale_intensity(world, 1.25)
+                }
+                "light_dimmer" => world.read_resource::<LightEditor>().scale_intensity(world, 0.8),
+                "light_color" => world.read_resource::<LightEditor>().cycle_color(world),
+                "light_add" => {
+                    let position = {
+                        let cameras = world.read_storage::<Camera>();
+                        let transforms = world.read_storage::<Transform>();
+                        (&cameras, &transforms)
+                            .join()
+                            .next()
+                            .map_or_else(Point3::origin, |(_, t)| Point3::from(*t.translation()))
+                    };
+                    add_point_light(world, position);
+                }
+                "light_remove" => {
+                    if let Err(e) = remove_selected_light(world) {
+                        eprintln!("Could not remove the light: {}", e);
+                    }
+                }
+                "light_save" => {
+                    match LightRig::capture(world).write(&self.lighting_path) {
+                        Ok(()) => println!("Saved lighting to {}", self.lighting_path.display()),
+                        Err(e) => eprintln!("Could not save the lighting rig: {}", e),
+                    }
+                }
+                "play_camera_path" => {
+                    let path = self.assets_dir.join(&self.camera_path);
+                    if let Err(e) = toggle_camera_path(world, &path) {
+                        eprintln!("Could not play camera path `{}`: {}", self.camera_path, e);
+                    }
+                }
+                preset if preset.starts_with("style_preset_") => {
+                    if let Ok(number) = preset["style_preset_".len()..].parse::<usize>() {
+                        let mut presets = world.write_resource::<StylePresets>();
+                        match number.checked_sub(1).and_then(|index| presets.select(index)) {
+                            Some(name) => println!("Style preset {}", name),
+                            None => println!("No style preset {}", number),
+                        }
+                    }
+                }
+                bookmark if bookmark.starts_with("bookmark_") => {
+                    let (saving, alt) = {
+                        let input = world.read_resource::<InputHandler<StringBindings>>();
+                        (
+                            input.key_is_down(VirtualKeyCode::LControl)
+                                || input.key_is_down(VirtualKeyCode::RControl),
+                            input.key_is_down(VirtualKeyCode::LAlt)
+                                || input.key_is_down(VirtualKeyCode::RAlt),
+                        )
+                    };
+                    // Alt+number selects a style preset instead.
+                    if alt {
+                        return Trans::None;
+                    }
+                    if let Ok(slot) = bookmark["bookmark_".len()..].parse() {
+                        let result = if saving {
+                            save_bookmark(world, slot, &self.bookmarks_path)
+                        } else {
+                            restore_bookmark(world, slot)
+                        };
+                        if let Err(e) = result {
+                            eprintln!("Camera bookmark {}: {}", slot, e);
+                        }
+                    }
+                }
+                "cycle_selection" => {
+                    let next = self
+                        .models
+                        .iter()
+                        .position(|&model| Some(model) == self.entity)
+                        .map_or(0, |index| (index + 1) % self.models.len().max(1));
+                    if let Some(&entity) = self.models.get(next) {
+                        self.select(world, entity);
+                    }
+                }
+                "anim_list" => {
+                    if let Some(entity) = self.entity {
+                        if let Some(clips) = world.read_storage::<AnimationClips>().get(entity) {
+                            let current = world.read_resource::<Scene>().animations.get(&entity).copied();
+                            print_animations(clips, current);
+                        }
+                    }
+                }
+                "cycle_step" => {
+                    if let Some(entity) = self.entity {
+                        let mut steps = world.write_storage::<AnimationStep>();
+                        if let Ok(entry) = steps.entry(entity) {
+                            let step = entry.or_insert_with(AnimationStep::default);
+                            step.cycle();
+                            println!("Animating on {}s", step.frames);
+                        }
+                    }
+                }
+                "anim_manual" => toggle_manual_clock(world, self.entity),
+                "anim_scrub_forward" => scrub_animation(world, self.entity, 1.0),
+                "anim_scrub_backward" => scrub_animation(world, self.entity, -1.0),
+                "anim_scrub_forward_second" => scrub_seconds(world, self.entity, 1.0),
+                "anim_scrub_backward_second" => scrub_seconds(world, self.entity, -1.0),
+                "toggle_onion" => {
+                    let mut onion = world.write_resource::<OnionSkin>();
+                    onion.enabled = !onion.enabled;
+                }
+                "cycle_onion_spacing" => {
+                    let mut onion = world.write_resource::<OnionSkin>();
+                    onion.cycle_spacing();
+                    println!("Onion skin every {} frames", onion.spacing);
+                }
+                "toggle_boil" => {
+                    let mut boil = world.write_resource::<LineBoil>();
+                    boil.enabled = !boil.enabled;
+                }
+                "export_svg" => {
+                    let path = PathBuf::from("line_drawing.svg");
+                    match export_svg(world, &path) {
+                        Ok(()) => println!("Exported line drawing to {}", path.display()),
+                        Err(e) => eprintln!(
+                            "Could not export the line drawing to {}: {}",
+                            path.display(),
+                            e
+                        ),
+                    }
+                }
+                _ => {}
+            }
         }
+        Trans::None
     }
 }
 
-fn toggle_or_cycle_animation(
-    entity: Option<Entity>,
-    scene: &mut Scene,
-    sets: &ReadStorage<'_, AnimationSet<usize, Transform>>,
-    controls: &mut WriteStorage<'_, AnimationControlSet<usize, Transform>>,
-) {
-    if let Some((entity, Some(animations))) = entity.map(|entity| (entity, sets.get(entity))) {
-        if animations.animations.len() > scene.animation_index {
-            let animation = animations.animations.get(&scene.animation_index).unwrap();
-            let set = get_animation_set::<usize, Transform>(controls, entity).unwrap();
-
-            // end the last animation before starting new one
-            // model needs to be in rest state before starting an animation
-            let mut last_animation = animations.animations.len() - 1;
-            if scene.animation_index != 0 {
-                last_animation = scene.animation_index - 1;
+impl AniObject {
+    /// Starts loading `self.scene`; `update` instantiates it once loaded.
+    fn load_models(&mut self, world: &mut World) {
+        self.progress = Some(ProgressCounter::default());
+
+        let source = &self.scene;
+        let progress = self.progress.as_mut().unwrap();
+        world.exec(
+            |(loader, mut scene): (PrefabLoader<'_, AnimationPrefabData>, Write<'_, Scene>)| {
+                scene.anim_handle = Some(match source {
+                    SceneSource::Prefab(path) => loader.load(path.as_str(), RonFormat, progress),
+                    SceneSource::Model { path, format } => {
+                        loader.load_from_data(model_prefab(path, *format), progress)
+                    }
+                });
+            },
+        );
+    }
+
+    /// Deletes the loaded models and everything under them, leaving the camera, lights and
+    /// style settings alone.
+    fn unload_models(&mut self, world: &mut World) {
+        let roots = self
+            .models
+            .drain(..)
+            .chain(self.prefab_entity.take())
+            .collect::<Vec<_>>();
+        let mut doomed = roots.clone();
+        {
+            let hierarchy = world.read_resource::<ParentHierarchy>();
+            let entities = world.entities();
+            for &root in &roots {
+                doomed.extend((&*entities, &hierarchy.all_children(root)).join().map(|(e, _)| e));
             }
-            set.abort(last_animation);
-
-            if set.has_animation(scene.animation_index) {
-                set.toggle(scene.animation_index);
-            } else {
-                println!("Running animation {}", scene.animation_index);
-                set.add_animation(
-                    scene.animation_index,
-                    animation,
-                    EndControl::Loop(None),
-                    1.0,
-                    AnimationCommand::Start,
-                );
+        }
+        doomed.sort();
+        doomed.dedup();
+        if let Err(e) = world.delete_entities(&doomed) {
+            eprintln!("Could not unload the previous model: {}", e);
+        }
+        world.write_resource::<Scene>().animations.clear();
+        world.write_resource::<OnionSkin>().target = None;
+        self.entity = None;
+        self.initialized = false;
+    }
+
+    /// Points the animation controls and onion skin at `entity`.
+    fn select(&mut self, world: &mut World, entity: Entity) {
+        self.entity = Some(entity);
+        world.write_resource::<OnionSkin>().target = Some(entity);
+
+        let index = self.models.iter().position(|&model| model == entity).unwrap_or(0);
+        let clips = world.read_storage::<AnimationClips>();
+        match clips.get(entity) {
+            Some(clips) => {
+                println!("Selected model {}: {}", index, clips.model);
+                let current = world.read_resource::<Scene>().animations.get(&entity).copied();
+                print_animations(clips, current);
             }
-            scene.animation_index += 1;
-            if scene.animation_index >= animations.animations.len() {
-                scene.animation_index = 0;
+            None => println!("Selected model {}", index),
+        }
+    }
+
+    /// Starts the clip requested on the command line, posing it if a time was given.
+    fn start_requested_animation(&mut self, world: &mut World, entity: Entity) {
+        // Only applies to the models loaded at startup.
+        let (start_animation, start_time) = (self.start_animation.take(), self.start_time.take());
+        if start_animation.is_none() && start_time.is_none() {
+            return;
+        }
+        let id = match &start_animation {
+            None => 0,
+            Some(name) => {
+                let id = world
+                    .read_storage::<AnimationClips>()
+                    .get(entity)
+                    .and_then(|clips| clips.id(name))
+                    .or_else(|| name.parse().ok());
+                match id {
+                    Some(id) => id,
+                    None => {
+                        eprintln!("No animation named {}", name);
+                        return;
+                    }
+                }
             }
+        };
+        control_animation(world, Some(entity), PlaybackCommand::Play(id));
+        if let Some(time) = start_time {
+            let _ = world
+                .write_storage::<ManualClock>()
+                .insert(entity, ManualClock::new(id, time));
+        }
+    }
+
+    /// Shows the selected model, its running clip and the model list on the HUD.
+    fn update_hud(&self, world: &World) {
+        let clips = world.read_storage::<AnimationClips>();
+        let clips = self.entity.and_then(|entity| clips.get(entity));
+        let current = self
+            .entity
+            .and_then(|entity| world.read_resource::<Scene>().animations.get(&entity).copied());
+        let posed = self
+            .entity
+            .map_or(false, |entity| world.read_storage::<ManualClock>().contains(entity));
+
+        let mut hud = world.write_resource::<Hud>();
+        hud.style = match world.read_resource::<StylePresets>().active_name() {
+            Some(preset) => format!("{} ({})", self.style.name(), preset),
+            None => self.style.name().to_string(),
+        };
+        hud.model = match (clips, &self.scene) {
+            (Some(clips), _) => clips.model.clone(),
+            (None, SceneSource::Model { path, .. }) => path.clone(),
+            (None, SceneSource::Prefab(path)) => path.clone(),
+        };
+        hud.animation = match (clips, current) {
+            (Some(clips), Some(id)) if posed => format!("{} (scrubbing)", clips.name(id)),
+            (Some(clips), Some(id)) => clips.name(id),
+            _ => "none".to_string(),
+        };
+        if hud.show_models {
+            let heading = format!("Models ({}):", self.browser.models.len());
+            let rows = self.browser.listing(hud.model_rows());
+            hud.models = std::iter::once(heading).chain(rows).collect();
+        }
+    }
+
+    /// Whether a pose requested with `--time` has been applied yet.
+    fn is_posed(&self, world: &World) -> bool {
+        self.entity
+            .and_then(|entity| world.read_storage::<ManualClock>().get(entity).copied())
+            .map_or(true, |clock| clock.is_posed())
+    }
+}
+
+/// Combined world space bounds of every `LineSource`, in its bind pose.
+fn model_bounds(world: &World) -> amethyst::Result<Bounds> {
+    let mut meshes = world.write_resource::<LineMeshCache>();
+    let sources = world.read_storage::<LineSource>();
+    let parents = world.read_storage::<Parent>();
+    let transforms = world.read_storage::<Transform>();
+
+    let mut bounds: Option<Bounds> = None;
+    for (source, transform, parent) in (&sources, transforms.maybe(), parents.maybe()).join() {
+        // Global matrices of freshly loaded roots are only computed at the end of the frame.
+        let matrix = match (transform, parent) {
+            (Some(transform), None) => transform.matrix(),
+            (Some(transform), Some(_)) => *transform.global_matrix(),
+            (None, _) => Matrix4::identity(),
+        };
+        if let Some(mesh_bounds) = Bounds::of_mesh(meshes.get(&source.model)?, &matrix) {
+            bounds = Some(bounds.map_or(mesh_bounds, |b| b.union(&mesh_bounds)));
+        }
+    }
+    bounds.ok_or_else(|| Error::from_string("no model geometry to frame"))
+}
+
+/// Points the camera at the bounds of the loaded models.
+fn frame_camera(world: &mut World) -> amethyst::Result<()> {
+    let bounds = model_bounds(world)?;
+    let mut cameras = world.write_storage::<Camera>();
+    let mut transforms = world.write_storage::<Transform>();
+    let mut orbits = world.write_storage::<OrbitCamera>();
+
+    let (camera, transform, orbit) = (&mut cameras, &mut transforms, (&mut orbits).maybe())
+        .join()
+        .next()
+        .ok_or_else(|| Error::from_string("no camera to frame with"))?;
+    frame_bounds(camera, transform, &bounds);
+    if let Some(orbit) = orbit {
+        let turntable = orbit.turntable;
+        *orbit = OrbitCamera::looking_at(transform, bounds.center());
+        orbit.turntable = turntable;
+    }
+    Ok(())
+}
+
+/// Switches the camera between the fly controls and orbiting the loaded models, optionally
+/// spinning as a turntable.
+fn toggle_orbit_camera(world: &mut World, turntable: bool) {
+    let target = model_bounds(world)
+        .map(|bounds| bounds.center())
+        .unwrap_or_else(|_| Point3::origin());
+    let entities = world.entities();
+    let cameras = world.read_storage::<Camera>();
+    let transforms = world.read_storage::<Transform>();
+    let mut orbits = world.write_storage::<OrbitCamera>();
+    let mut fly_tags = world.write_storage::<FlyControlTag>();
+    let mut hide_cursor = world.write_resource::<HideCursor>();
+
+    let (entity, _, transform) = match (&*entities, &cameras, &transforms).join().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    match orbits.get_mut(entity) {
+        // Turning the turntable on or off keeps orbiting.
+        Some(orbit) if turntable => orbit.turntable = !orbit.turntable,
+        Some(_) => {
+            orbits.remove(entity);
+            let _ = fly_tags.insert(entity, FlyControlTag);
+            hide_cursor.hide = true;
+        }
+        None => {
+            fly_tags.remove(entity);
+            let mut orbit = OrbitCamera::looking_at(transform, target);
+            orbit.turntable = turntable;
+            let _ = orbits.insert(entity, orbit);
+            // The orbit camera is dragged with a visible cursor.
+            hide_cursor.hide = false;
+        }
+    }
+}
+
+/// Point the camera is looking at: the orbit target, or else the center of the loaded models.
+fn camera_focus(world: &World) -> Point3<f32> {
+    let orbits = world.read_storage::<OrbitCamera>();
+    match orbits.join().next() {
+        Some(orbit) => orbit.target,
+        None => model_bounds(world)
+            .map(|bounds| bounds.center())
+            .unwrap_or_else(|_| Point3::origin()),
+    }
+}
+
+/// Switches the camera between perspective and orthographic projection, keeping the models
+/// framed the same.
+fn toggle_projection_mode(world: &mut World) {
+    let focus = camera_focus(world);
+    let entities = world.entities();
+    let mut cameras = world.write_storage::<Camera>();
+    let mut transforms = world.write_storage::<Transform>();
+    let mut ortho_views = world.write_storage::<OrthographicView>();
+    let mut orbits = world.write_storage::<OrbitCamera>();
+
+    let (entity, camera, transform) =
+        match (&*entities, &mut cameras, &mut transforms).join().next() {
+            Some(camera) => camera,
+            None => return,
+        };
+    let mut view = ortho_views.remove(entity);
+    let distance = toggle_projection(camera, transform, &mut view, &focus);
+    if let Some(orbit) = orbits.get_mut(entity) {
+        orbit.distance = distance;
+    }
+    match view {
+        Some(view) => {
+            let _ = ortho_views.insert(entity, view);
+            println!("Orthographic projection");
         }
+        None => println!("Perspective projection"),
+    }
+}
+
+/// Looks at the models from one of the axonometric presets, switching to an orthographic
+/// projection if needed.
+fn apply_view_preset(world: &mut World, preset: ViewPreset) {
+    let focus = camera_focus(world);
+    {
+        let entities = world.entities();
+        let mut transforms = world.write_storage::<Transform>();
+        let mut orbits = world.write_storage::<OrbitCamera>();
+        let mut players = world.write_storage::<CameraPathPlayer>();
+        let cameras = world.read_storage::<Camera>();
+
+        let (entity, _, transform) =
+            match (&*entities, &cameras, &mut transforms).join().next() {
+                Some(camera) => camera,
+                None => return,
+            };
+        let distance = (transform.translation() - focus.coords).norm().max(1.0e-3);
+        preset.apply(transform, &focus, distance);
+        players.remove(entity);
+        if let Some(orbit) = orbits.get_mut(entity) {
+            let turntable = orbit.turntable;
+            *orbit = OrbitCamera::looking_at(transform, focus);
+            orbit.turntable = turntable;
+        }
+    }
+    let perspective = world.read_storage::<OrthographicView>().join().next().is_none();
+    if perspective {
+        toggle_projection_mode(world);
+    }
+}
+
+/// Stores the camera placement in bookmark `slot` and writes every bookmark to `path`.
+fn save_bookmark(world: &World, slot: u8, path: &Path) -> amethyst::Result<()> {
+    let bookmark = {
+        let cameras = world.read_storage::<Camera>();
+        let transforms = world.read_storage::<Transform>();
+        let (camera, transform) = (&cameras, &transforms)
+            .join()
+            .next()
+            .ok_or_else(|| Error::from_string("no camera to bookmark"))?;
+        CameraBookmark::capture(camera, transform)
+    };
+    let mut bookmarks = world.write_resource::<CameraBookmarks>();
+    bookmarks.slots.insert(slot, bookmark);
+    bookmarks.write(path)?;
+    println!("Saved camera bookmark {}", slot);
+    Ok(())
+}
+
+/// Moves the camera to bookmark `slot`, stopping any camera path.
+fn restore_bookmark(world: &World, slot: u8) -> amethyst::Result<()> {
+    let bookmarks = world.read_resource::<CameraBookmarks>();
+    let bookmark = bookmarks
+        .slots
+        .get(&slot)
+        .ok_or_else(|| Error::from_string("nothing saved, hold Ctrl to save"))?;
+    let entities = world.entities();
+    let mut cameras = world.write_storage::<Camera>();
+    let mut transforms = world.write_storage::<Transform>();
+    let mut orbits = world.write_storage::<OrbitCamera>();
+    let mut players = world.write_storage::<CameraPathPlayer>();
+
+    let (entity, camera, transform) = (&*entities, &mut cameras, &mut transforms)
+        .join()
+        .next()
+        .ok_or_else(|| Error::from_string("no camera to move"))?;
+    bookmark.apply(camera, transform);
+    players.remove(entity);
+    if let Some(orbit) = orbits.get_mut(entity) {
+        let turntable = orbit.turntable;
+        *orbit = OrbitCamera::looking_at(transform, orbit.target);
+        orbit.turntable = turntable;
+    }
+    Ok(())
+}
+
+/// Starts the camera path in the RON file at `path`, or stops the one playing.
+fn toggle_camera_path(world: &mut World, path: &Path) -> amethyst::Result<()> {
+    let entities = world.entities();
+    let mut cameras = world.write_storage::<Camera>();
+    let mut transforms = world.write_storage::<Transform>();
+    let mut players = world.write_storage::<CameraPathPlayer>();
+
+    let (entity, camera, transform) = (&*entities, &mut cameras, &mut transforms)
+        .join()
+        .next()
+        .ok_or_else(|| Error::from_string("no camera to move"))?;
+    if players.remove(entity).is_some() {
+        return Ok(());
+    }
+    let camera_path = CameraPath::load(path)?;
+    if camera_path.keyframes.is_empty() {
+        return Err(Error::from_string("the path has no keyframes"));
     }
+    let player = CameraPathPlayer::new(camera_path);
+    // Place the camera right away so a headless export sees the first keyframe.
+    player.apply(camera, transform);
+    players.insert(entity, player)?;
+    Ok(())
+}
+
+/// Writes the visible feature lines of every `LineSource`, as seen by the first camera, to `path`.
+///
+/// Skinned models are exported in their current pose, just like the on-screen strokes, while
+/// meshes animated by moving their own glTF nodes keep the placement baked into their `LineMesh`.
+fn export_svg(world: &World, path: &Path) -> amethyst::Result<()> {
+    let mut meshes = world.write_resource::<LineMeshCache>();
+    let dimensions = world.read_resource::<ScreenDimensions>();
+    let hierarchy = world.read_resource::<ParentHierarchy>();
+    let entities = world.entities();
+    let cameras = world.read_storage::<Camera>();
+    let transforms = world.read_storage::<Transform>();
+    let sources = world.read_storage::<LineSource>();
+    let skins = world.read_storage::<Skin>();
+
+    let (camera, camera_transform) = (&cameras, &transforms)
+        .join()
+        .next()
+        .ok_or_else(|| Error::from_string("no camera to export from"))?;
+
+    let mut world_meshes: Vec<(LineMesh, LineSettings)> = Vec::new();
+    for (entity, source, transform) in (&*entities, &sources, transforms.maybe()).join() {
+        let mesh = meshes.get(&source.model)?;
+        let global = transform.map_or_else(Matrix4::identity, |t| *t.global_matrix());
+        let joints = joint_matrices(mesh, entity, &hierarchy, &skins, &transforms);
+        world_meshes.push((mesh.posed(&global, &joints), source.settings));
+    }
+
+    let svg = render_line_drawing(
+        &world_meshes,
+        camera.as_matrix(),
+        camera_transform.global_matrix(),
+        matches!(camera.projection(), Projection::Orthographic(_)),
+        (dimensions.width() as u32, dimensions.height() as u32),
+        &SvgStyle::default(),
+    );
+    std::fs::write(path, svg)?;
+    Ok(())
+}
+
+/// Applies a playback command to the animations of `entity`.
+fn control_animation(world: &mut World, entity: Option<Entity>, command: PlaybackCommand) {
+    let entity = match entity {
+        Some(entity) => entity,
+        None => return,
+    };
+    let mut scene = world.write_resource::<Scene>();
+    let sets = world.read_storage::<AnimationSet<usize, Transform>>();
+    let clips = world.read_storage::<AnimationClips>();
+    let mut controls = world.write_storage::<AnimationControlSet<usize, Transform>>();
+    let mut steps = world.write_storage::<AnimationStep>();
+    let mut crossfades = world.write_storage::<CrossFade>();
+    let mut clocks = world.write_storage::<ManualClock>();
+
+    let animations = match sets.get(entity) {
+        Some(animations) => animations,
+        None => return,
+    };
+    let set = match get_animation_set::<usize, Transform>(&mut controls, entity) {
+        Some(set) => set,
+        None => return,
+    };
+    let step = match steps.entry(entity) {
+        Ok(entry) => entry.or_insert_with(AnimationStep::default),
+        Err(_) => return,
+    };
+
+    let previous = scene.animations.get(&entity).copied();
+    let current = apply_playback(
+        command,
+        previous,
+        animations,
+        set,
+        step,
+        crossfades.get_mut(entity),
+    );
+    match current {
+        Some(id) => scene.animations.insert(entity, id),
+        None => scene.animations.remove(&entity),
+    };
+    if let (Some(clock), Some(id)) = (clocks.get_mut(entity), current) {
+        if current != previous {
+            clock.set_clip(id);
+        }
+    }
+    match (command, current) {
+        (PlaybackCommand::Faster, _) | (PlaybackCommand::Slower, _) => {
+            println!("Playback speed {}x", step.speed)
+        }
+        (_, Some(id)) if current != previous => {
+            let name = clips
+                .get(entity)
+                .map(|clips| clips.name(id))
+                .unwrap_or_else(|| id.to_string());
+            println!("Running animation {}", name);
+        }
+        _ => {}
+    }
+}
+
+/// Switches `entity` between playing its clip and posing it with a `ManualClock`.
+fn toggle_manual_clock(world: &mut World, entity: Option<Entity>) {
+    let entity = match entity {
+        Some(entity) => entity,
+        None => return,
+    };
+    let current = world.read_resource::<Scene>().animations.get(&entity).copied();
+    let mut clocks = world.write_storage::<ManualClock>();
+    if clocks.remove(entity).is_some() {
+        // AnimationStepSystem restores the playback rate from here on.
+        println!("Playing animation");
+    } else if let Some(id) = current {
+        let _ = clocks.insert(entity, ManualClock::new(id, 0.0));
+        println!("Scrubbing animation, t = 0.000s");
+    }
+}
+
+/// Moves the manual clock of `entity` by `frames` animation frames.
+fn scrub_animation(world: &mut World, entity: Option<Entity>, frames: f32) {
+    let frame_rate = entity
+        .and_then(|entity| world.read_storage::<AnimationStep>().get(entity).cloned())
+        .unwrap_or_default()
+        .frame_rate;
+    scrub_seconds(world, entity, frames / frame_rate);
+}
+
+/// Moves the manual clock of `entity` by `seconds`, if it is being scrubbed.
+fn scrub_seconds(world: &mut World, entity: Option<Entity>, seconds: f32) {
+    if let Some(clock) = entity.and_then(|entity| {
+        world
+            .write_storage::<ManualClock>()
+            .get_mut(entity)
+            .map(|clock| {
+                clock.scrub(seconds);
+                *clock
+            })
+    }) {
+        println!("t = {:.3}s", clock.time());
+    }
+}
+
+fn print_animations(clips: &AnimationClips, current: Option<usize>) {
+    println!("Animations:");
+    for (id, name) in clips.names.iter().enumerate() {
+        let marker = if current == Some(id) { '>' } else { ' ' };
+        println!("{} {}: {}", marker, id, name);
+    }
+}
+
+/// Prefab for a single model given on the command line, with the default NPR settings.
+fn model_prefab(path: &str, format: ModelFormat) -> Prefab<AnimationPrefabData> {
+    let data = match format {
+        ModelFormat::Gltf | ModelFormat::Glb => AnimationPrefabData {
+            gltf: Some(AssetPrefab::File(path.to_string(), GltfSceneFormat::default())),
+            tag: Some(Tag::default()),
+            lines: Some(LineSource {
+                model: path.to_string(),
+                settings: Default::default(),
+            }),
+            clips: Some(AnimationClips {
+                model: path.to_string(),
+                names: Vec::new(),
+            }),
+            crossfade: Some(CrossFade::default()),
+            ..Default::default()
+        },
+        ModelFormat::Obj => AnimationPrefabData {
+            obj: Some(ObjPrefab::new(path)),
+            transform: Some(Transform::default()),
+            tag: Some(Tag::default()),
+            lines: Some(LineSource {
+                model: path.to_string(),
+                settings: Default::default(),
+            }),
+            ..Default::default()
+        },
+    };
+    Prefab::new_main(data)
 }
 
 fn initialize_camera(world: &mut World) {
     let mut transform = Transform::default();
     transform.set_translation_xyz(0.0, 5.0, 30.0);
 
+    let (width, height) = {
+        let dimensions = world.read_resource::<ScreenDimensions>();
+        (dimensions.width(), dimensions.height())
+    };
     world
         .create_entity()
-        .with(Camera::standard_3d(WIN_WIDTH, WIN_HEIGHT))
+        .with(Camera::standard_3d(width, height))
         .with(transform)
         .with(FlyControlTag)
         .build();
 }
 
 fn main() -> amethyst::Result<()> {
+    let app_root = application_root_dir()?;
+    let assets_dir = app_root.join("assets/");
+    let key_bindings_path = app_root.join("config/input.ron");
+
+    let settings = RenderSettings::load_or_default(&app_root.join("config/render.ron"))?;
     amethyst::Logger::from_config(amethyst::LoggerConfig {
-        level_filter: amethyst::LogLevelFilter::Error,
+        level_filter: settings.log_level()?,
         ..Default::default()
     })
     .start();
 
-    let app_root = application_root_dir()?;
-    let assets_dir = app_root.join("assets/");
-    let key_bindings_path = app_root.join("config/input.ron");
+    let options = Options::parse(std::env::args().skip(1), &assets_dir)?;
+    if options.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
 
-    let display_config = DisplayConfig {
-        title: "NPR Demo".to_string(),
-        dimensions: Some((WIN_WIDTH as u32, WIN_HEIGHT as u32)),
-        ..Default::default()
+    let mut display_config = load_display_config(&app_root.join("config/display.ron"))?;
+    if let Some(size) = options.window_size {
+        display_config.dimensions = Some(size);
+    }
+    let style = match options.style {
+        Some(style) => style,
+        None => settings.style()?,
+    };
+    let render_bundle = RenderingBundle::<DefaultBackend>::new().with_plugin(
+        RenderToWindow::from_config(display_config).with_clear(settings.clear_color),
+    );
+    // Only the cel pipelines can draw into the multisampled scene target.
+    let (scene_target, msaa_samples) = match style {
+        ShadingStyle::Cel => (SCENE_TARGET, settings.msaa_samples),
+        _ => {
+            if settings.msaa_samples > 1 {
+                log::warn!("MSAA is only supported by the cel style, ignoring msaa_samples");
+            }
+            (Target::Main, 1)
+        }
+    };
+    let render_bundle = match style {
+        ShadingStyle::Cel => render_bundle
+            .with_plugin(
+                RenderCustom3D::default()
+                    .with_skinning()
+                    .with_target(scene_target)
+                    .with_msaa_samples(msaa_samples),
+            )
+            .with_plugin(RenderAntialiasing::default().with_msaa_samples(msaa_samples)),
+        ShadingStyle::Shaded => {
+            render_bundle.with_plugin(RenderShaded3D::default().with_skinning())
+        }
+        ShadingStyle::Pbr => render_bundle.with_plugin(RenderPbr3D::default().with_skinning()),
     };
 
     let anim_data = GameDataBuilder::default()
         .with(AutoFovSystem::default(), "auto_fov", &[])
+        .with(LineBoilSystem, "line_boil", &[])
         .with_system_desc(
             PrefabLoaderSystemDesc::<AnimationPrefabData>::default(),
             "anim_loader",
@@ -222,9 +1087,17 @@ fn main() -> amethyst::Result<()> {
             "gltf_loader",
             &["anim_loader"],
         )
+        .with(AnimationStepSystem, "animation_step", &[])
+        .with(CrossFadeSystem, "animation_crossfade", &[])
+        .with(ManualClockSystem, "animation_manual_clock", &["animation_crossfade"])
         .with_bundle(
             AnimationBundle::<usize, Transform>::new("animation_control", "sampler_interpolation")
-                .with_dep(&["gltf_loader"]),
+                .with_dep(&[
+                    "gltf_loader",
+                    "animation_step",
+                    "animation_crossfade",
+                    "animation_manual_clock",
+                ]),
         )?
         .with_bundle(
             FlyControlBundle::<StringBindings>::new(
@@ -235,29 +1108,81 @@ fn main() -> amethyst::Result<()> {
             .with_sensitivity(0.1, 0.1)
             .with_speed(50.),
         )?
+        .with(OrbitCameraSystem::<StringBindings>::default(), "orbit_camera", &[])
+        .with(
+            CameraPathSystem,
+            "camera_path",
+            &["fly_movement", "free_rotation", "orbit_camera"],
+        )
         .with_bundle(TransformBundle::new().with_dep(&[
             "animation_control",
             "sampler_interpolation",
             "fly_movement",
+            "orbit_camera",
+            "camera_path",
         ]))?
         .with_bundle(VertexSkinningBundle::new().with_dep(&[
             "transform_system",
             "animation_control",
             "sampler_interpolation",
         ]))?
+        .with(OnionSkinSystem, "onion_skin", &["transform_system"])
+        .with(LightEditorSystem::<StringBindings>::default(), "light_editor", &[])
+        .with(Processor::<StylePreset>::new(), "style_preset_processor", &[])
+        .with(StylePresetSystem, "style_presets", &["style_preset_processor"])
+        .with_bundle(HotReloadBundle::new(HotReloadStrategy::every(30)))?
+        .with_bundle(FpsCounterBundle)?
+        .with(HudSystem, "hud", &["fps_counter_system"])
         .with_bundle(
-            RenderingBundle::<DefaultBackend>::new()
-                .with_plugin(RenderToWindow::from_config(display_config).with_clear(CLEAR))
-                .with_plugin(RenderCustom3D::default().with_skinning())
+            render_bundle
+                .with_plugin(
+                    RenderStrokes::default()
+                        .with_target(scene_target)
+                        .with_msaa_samples(msaa_samples),
+                )
+                .with_plugin(RenderDebugLines::default())
+                .with_plugin(RenderUi::default())
                 .with_plugin(RenderSkybox::default()),
         )?
         .with_bundle(
             InputBundle::<StringBindings>::new().with_bindings_from_file(&key_bindings_path)?,
-        )?;
+        )?
+        .with_bundle(UiBundle::<StringBindings>::new())?;
 
-    let state: AniObject = Default::default();
-    let mut scene = Application::new(assets_dir, state, anim_data)?;
+    let export_failed = Rc::new(Cell::new(false));
+    let state = AniObject {
+        assets_dir: assets_dir.clone(),
+        scene: options.scene,
+        svg_export: options.svg_export,
+        export_failed: Rc::clone(&export_failed),
+        start_animation: options.animation,
+        start_time: options.time,
+        start_camera_path: options.camera_path.is_some(),
+        camera_path: options
+            .camera_path
+            .unwrap_or_else(|| DEFAULT_CAMERA_PATH.to_string()),
+        bookmarks_path: app_root.join("config/camera_bookmarks.ron"),
+        lighting_path: app_root.join("config/lighting.ron"),
+        style,
+        preset: options.preset,
+        ..Default::default()
+    };
+    let (frame_limit, max_fps) = settings.frame_limit();
+    let mut scene = Application::build(assets_dir.clone(), state)?
+        .with_frame_limit(frame_limit, max_fps)
+        .with_resource(AssetsDir(assets_dir.clone()))
+        .with_resource(LineMeshCache::new(assets_dir))
+        .with_resource(DebugLines::new())
+        .with_resource(DebugLinesParams { line_width: 2.0 })
+        .with_resource(Antialiasing {
+            msaa_samples,
+            fxaa: settings.fxaa,
+        })
+        .build(anim_data)?;
     scene.run();
 
+    if export_failed.get() {
+        std::process::exit(1);
+    }
     Ok(())
 }
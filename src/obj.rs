@@ -0,0 +1,399 @@
+use amethyst::{
+    assets::{AssetStorage, Handle, Loader, PrefabData, ProgressCounter},
+    core::{
+        ecs::{Entities, Entity, Read, ReadExpect, WriteStorage},
+        math::Vector3,
+        Parent, Transform,
+    },
+    renderer::{
+        loaders::load_from_srgba,
+        palette::Srgba,
+        rendy::mesh::{MeshBuilder, Normal, Position, Tangent, TexCoord},
+        types::MeshData,
+        Material, MaterialDefaults, Mesh, Texture,
+    },
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Base color used when an OBJ group has no material, or its material has no `Kd`.
+const DEFAULT_BASE_COLOR: [f32; 3] = [0.8, 0.8, 0.8];
+
+/// Directory the `Loader` reads assets from, used by `ObjPrefab` to find OBJ files and their
+/// MTL libraries.
+#[derive(Clone, Debug, Default)]
+pub struct AssetsDir(pub PathBuf);
+
+/// A static Wavefront OBJ model, drawn like any other mesh by `RenderCustom3D`.
+///
+/// Every material (`usemtl`) of the model becomes its own mesh on a child entity, with the
+/// diffuse color (`Kd`) of that material in the MTL library as its base color.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ObjPrefab {
+    /// OBJ path relative to the assets directory.
+    pub file: String,
+    #[serde(skip)]
+    parts: Vec<(Handle<Mesh>, Handle<Material>)>,
+}
+
+impl ObjPrefab {
+    pub fn new<S: Into<String>>(file: S) -> Self {
+        ObjPrefab {
+            file: file.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> PrefabData<'a> for ObjPrefab {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Loader>,
+        ReadExpect<'a, AssetsDir>,
+        ReadExpect<'a, MaterialDefaults>,
+        Read<'a, AssetStorage<Mesh>>,
+        Read<'a, AssetStorage<Texture>>,
+        Read<'a, AssetStorage<Material>>,
+        WriteStorage<'a, Parent>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Handle<Mesh>>,
+        WriteStorage<'a, Handle<Material>>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (entities, _, _, _, _, _, _, parents, transforms, meshes, materials): &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        for (mesh, material) in &self.parts {
+            let part = entities.create();
+            parents.insert(part, Parent { entity })?;
+            transforms.insert(part, Transform::default())?;
+            meshes.insert(part, mesh.clone())?;
+            materials.insert(part, material.clone())?;
+        }
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (_, loader, assets_dir, defaults, mesh_storage, textures, material_storage, _, _, _, _): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        let path = assets_dir.0.join(&self.file);
+        let text = std::fs::read_to_string(&path)?;
+        let colors = diffuse_colors(&path, &text);
+        self.parts = read_groups(&text)
+            .into_iter()
+            .map(|group| {
+                let color = group
+                    .material
+                    .as_ref()
+                    .and_then(|material| colors.get(material))
+                    .copied()
+                    .unwrap_or(DEFAULT_BASE_COLOR);
+                let albedo = loader.load_from_data(
+                    load_from_srgba(Srgba::new(color[0], color[1], color[2], 1.0)).into(),
+                    &mut *progress,
+                    textures,
+                );
+                let material = loader.load_from_data(
+                    Material {
+                        albedo,
+                        ..defaults.0.clone()
+                    },
+                    &mut *progress,
+                    material_storage,
+                );
+                let mesh = loader.load_from_data(group.mesh_data(), &mut *progress, mesh_storage);
+                (mesh, material)
+            })
+            .collect();
+        Ok(true)
+    }
+}
+
+/// Triangles of an OBJ file sharing one material, as unindexed vertex attributes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjGroup {
+    /// Name given to `usemtl`, `None` for faces before the first `usemtl`.
+    pub material: Option<String>,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tex_coords: Vec<[f32; 2]>,
+}
+
+impl ObjGroup {
+    fn mesh_data(&self) -> MeshData {
+        // Tangents only matter for normal maps, which OBJ materials do not get here; they are
+        // derived from the texture coordinates so the vertex layout matches glTF meshes.
+        let tangents = self
+            .positions
+            .chunks_exact(3)
+            .zip(self.tex_coords.chunks_exact(3))
+            .flat_map(|(p, uv)| {
+                let tangent = face_tangent(p, uv);
+                vec![Tangent(tangent); 3]
+            })
+            .collect::<Vec<_>>();
+        let positions = self
+            .positions
+            .iter()
+            .map(|&p| Position(p))
+            .collect::<Vec<_>>();
+        let normals = self.normals.iter().map(|&n| Normal(n)).collect::<Vec<_>>();
+        let tex_coords = self
+            .tex_coords
+            .iter()
+            .map(|&t| TexCoord(t))
+            .collect::<Vec<_>>();
+        MeshBuilder::new()
+            .with_vertices(positions)
+            .with_vertices(normals)
+            .with_vertices(tangents)
+            .with_vertices(tex_coords)
+            .into()
+    }
+}
+
+/// Splits the faces of an OBJ file by material, triangulating polygons as fans.
+///
+/// Faces without normals get flat normals and faces without texture coordinates get zeros.
+/// Groups come out in the order their material is first used.
+pub fn read_groups(obj: &str) -> Vec<ObjGroup> {
+    let (mut positions, mut normals, mut tex_coords) = (Vec::new(), Vec::new(), Vec::new());
+    let mut groups: Vec<ObjGroup> = vec![ObjGroup::default()];
+    let mut current = 0;
+    for line in obj.lines() {
+        let mut words = line.split_whitespace();
+        let keyword = words.next();
+        let mut numbers = || {
+            words
+                .next()
+                .and_then(|w| w.parse::<f32>().ok())
+                .unwrap_or(0.0)
+        };
+        match keyword {
+            Some("v") => positions.push([numbers(), numbers(), numbers()]),
+            Some("vn") => normals.push([numbers(), numbers(), numbers()]),
+            // OBJ texture coordinates start at the bottom left.
+            Some("vt") => tex_coords.push([numbers(), 1.0 - numbers()]),
+            Some("usemtl") => {
+                let material = Some(line.trim()["usemtl".len()..].trim().to_string());
+                current = match groups.iter().position(|g| g.material == material) {
+                    Some(index) => index,
+                    None => {
+                        groups.push(ObjGroup {
+                            material,
+                            ..Default::default()
+                        });
+                        groups.len() - 1
+                    }
+                };
+            }
+            Some("f") => {
+                let corners = line
+                    .split_whitespace()
+                    .skip(1)
+                    .filter_map(|corner| {
+                        let mut indices = corner.split('/');
+                        let position = resolve(indices.next(), positions.len())?;
+                        let tex_coord = resolve(indices.next(), tex_coords.len());
+                        let normal = resolve(indices.next(), normals.len());
+                        Some((position, tex_coord, normal))
+                    })
+                    .collect::<Vec<_>>();
+                let group = &mut groups[current];
+                for i in 1..corners.len().saturating_sub(1) {
+                    let triangle = [corners[0], corners[i], corners[i + 1]];
+                    let p = [
+                        positions[triangle[0].0],
+                        positions[triangle[1].0],
+                        positions[triangle[2].0],
+                    ];
+                    let flat = face_normal(&p);
+                    for (k, &(_, tex_coord, normal)) in triangle.iter().enumerate() {
+                        group.positions.push(p[k]);
+                        group.normals.push(normal.map_or(flat, |n| normals[n]));
+                        group
+                            .tex_coords
+                            .push(tex_coord.map_or([0.0, 0.0], |t| tex_coords[t]));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    groups.retain(|group| !group.positions.is_empty());
+    groups
+}
+
+/// Resolves a 1-based or negative (relative) OBJ index into one of `count` elements.
+fn resolve(index: Option<&str>, count: usize) -> Option<usize> {
+    let index = index?.parse::<i64>().ok()?;
+    let index = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if index >= 0 && (index as usize) < count {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+fn face_normal(p: &[[f32; 3]; 3]) -> [f32; 3] {
+    let (a, b, c) = (
+        Vector3::from(p[0]),
+        Vector3::from(p[1]),
+        Vector3::from(p[2]),
+    );
+    let normal = (b - a)
+        .cross(&(c - a))
+        .try_normalize(std::f32::EPSILON)
+        .unwrap_or_else(Vector3::z);
+    [normal.x, normal.y, normal.z]
+}
+
+fn face_tangent(p: &[[f32; 3]], uv: &[[f32; 2]]) -> [f32; 4] {
+    let (e1, e2) = (
+        Vector3::from(p[1]) - Vector3::from(p[0]),
+        Vector3::from(p[2]) - Vector3::from(p[0]),
+    );
+    let (du1, dv1) = (uv[1][0] - uv[0][0], uv[1][1] - uv[0][1]);
+    let (du2, dv2) = (uv[2][0] - uv[0][0], uv[2][1] - uv[0][1]);
+    let det = du1 * dv2 - du2 * dv1;
+    let tangent = if det.abs() > std::f32::EPSILON {
+        (e1 * dv2 - e2 * dv1) / det
+    } else {
+        e1
+    };
+    let tangent = tangent
+        .try_normalize(std::f32::EPSILON)
+        .unwrap_or_else(Vector3::x);
+    [tangent.x, tangent.y, tangent.z, 1.0]
+}
+
+/// Diffuse colors (`Kd`) of every material in the MTL libraries `obj` refers to.
+pub fn diffuse_colors(obj_path: &Path, obj: &str) -> HashMap<String, [f32; 3]> {
+    let dir = obj_path.parent().unwrap_or_else(|| Path::new(""));
+    let mut colors = HashMap::new();
+    for line in obj.lines() {
+        if let Some(library) = line.trim().strip_prefix("mtllib ") {
+            // A missing library is common in exported files; its groups use the default color.
+            if let Ok(mtl) = std::fs::read_to_string(dir.join(library.trim())) {
+                for (name, color) in read_mtl(&mtl) {
+                    colors.entry(name).or_insert(color);
+                }
+            }
+        }
+    }
+    colors
+}
+
+/// `Kd` of every material of an MTL file that has one.
+fn read_mtl(mtl: &str) -> Vec<(String, [f32; 3])> {
+    let mut colors = Vec::new();
+    let mut current: Option<&str> = None;
+    for line in mtl.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("newmtl") => current = Some(line.trim()["newmtl".len()..].trim()),
+            Some("Kd") => {
+                let mut channel = || words.next().and_then(|w| w.parse().ok());
+                if let (Some(name), Some(r), Some(g), Some(b)) =
+                    (current, channel(), channel(), channel())
+                {
+                    colors.push((name.to_string(), [r, g, b]));
+                }
+            }
+            _ => {}
+        }
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_MATERIALS: &str = "\
+mtllib parts.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+vn 0 0 1
+vt 0 0
+vt 1 1
+usemtl red
+f 1/1/1 2/1/1 3/2/1 4/2/1
+usemtl blue
+f 1 2 5
+usemtl red
+f -5 -3 -1
+";
+
+    #[test]
+    fn splits_faces_by_material() {
+        let groups = read_groups(TWO_MATERIALS);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].material.as_deref(), Some("red"));
+        assert_eq!(groups[1].material.as_deref(), Some("blue"));
+        // The quad is split into two triangles, then joined by the later `usemtl red` face.
+        assert_eq!(groups[0].positions.len(), 9);
+        assert_eq!(groups[1].positions.len(), 3);
+        assert_eq!(
+            groups[0].positions[6..],
+            [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn fills_in_missing_attributes() {
+        let groups = read_groups(TWO_MATERIALS);
+        assert_eq!(groups[0].normals[0], [0.0, 0.0, 1.0]);
+        assert_eq!(groups[0].tex_coords[2], [1.0, 0.0]);
+        // Face `1 2 5` has neither normals nor texture coordinates.
+        assert_eq!(groups[1].normals, vec![[0.0, -1.0, 0.0]; 3]);
+        assert_eq!(groups[1].tex_coords, vec![[0.0, 0.0]; 3]);
+    }
+
+    #[test]
+    fn faces_without_material_form_their_own_group() {
+        let groups = read_groups("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].material, None);
+    }
+
+    #[test]
+    fn resolves_relative_and_invalid_indices() {
+        assert_eq!(resolve(Some("1"), 3), Some(0));
+        assert_eq!(resolve(Some("-1"), 3), Some(2));
+        assert_eq!(resolve(Some("4"), 3), None);
+        assert_eq!(resolve(Some(""), 3), None);
+        assert_eq!(resolve(None, 3), None);
+    }
+
+    #[test]
+    fn reads_diffuse_colors() {
+        let mtl = "newmtl red\nKa 0 0 0\nKd 1 0 0\n\nnewmtl plain\nnewmtl blue\nKd 0 0 1\n";
+        assert_eq!(
+            read_mtl(mtl),
+            vec![
+                ("red".to_string(), [1.0, 0.0, 0.0]),
+                ("blue".to_string(), [0.0, 0.0, 1.0]),
+            ]
+        );
+    }
+}
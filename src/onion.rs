@@ -0,0 +1,361 @@
+use amethyst::{
+    animation::{
+        AnimationHierarchy, AnimationSampling, ControlState, EndControl, InterpolationPrimitive,
+        Sampler, SamplerControlSet, SamplerPrimitive, Skin,
+    },
+    assets::{AssetStorage, Handle},
+    core::{
+        ecs::{
+            Component, DenseVecStorage, Entities, Entity, Join, Read, ReadStorage, System,
+            WriteStorage,
+        },
+        math::Matrix4,
+        Hidden, Parent, Transform,
+    },
+    renderer::{palette::Srgba, resources::Tint, skinning::JointTransforms, Material, Mesh},
+};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+/// Onion skinning: translucent, outline-only ghosts of an animated model's past and future
+/// poses, for reviewing the spacing of an animation.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OnionSkin {
+    pub enabled: bool,
+    /// Number of ghosts drawn before the current pose.
+    pub past: u32,
+    /// Number of ghosts drawn after the current pose.
+    pub future: u32,
+    /// Animation frames between consecutive ghosts.
+    pub spacing: u32,
+    /// Frame rate the spacing is counted at.
+    pub frame_rate: f32,
+    pub past_color: [f32; 3],
+    pub future_color: [f32; 3],
+    /// Opacity of the ghosts nearest to the current pose; further ones fade out.
+    pub opacity: f32,
+    /// Root of the animated model, set by the application once it is loaded.
+    #[serde(skip)]
+    pub target: Option<Entity>,
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        OnionSkin {
+            enabled: false,
+            past: 2,
+            future: 2,
+            spacing: 3,
+            frame_rate: 24.0,
+            past_color: [0.9, 0.2, 0.2],
+            future_color: [0.2, 0.8, 0.3],
+            opacity: 0.6,
+            target: None,
+        }
+    }
+}
+
+const SPACINGS: [u32; 7] = [1, 2, 3, 4, 6, 8, 12];
+
+impl OnionSkin {
+    /// Cycles the frame spacing between ghosts.
+    pub fn cycle_spacing(&mut self) {
+        let next = SPACINGS.iter().position(|&s| s > self.spacing).unwrap_or(0);
+        self.spacing = SPACINGS[next];
+    }
+
+    /// Frame offsets of the ghosts relative to the current pose, nearest first.
+    fn offsets(&self) -> impl Iterator<Item = i32> + '_ {
+        let spacing = self.spacing.max(1) as i32;
+        (1..=self.past as i32)
+            .map(move |k| -k * spacing)
+            .chain((1..=self.future as i32).map(move |k| k * spacing))
+    }
+
+    /// Tint of the ghost `offset` frames away from the current pose.
+    fn tint(&self, offset: i32) -> Tint {
+        let (color, count) = if offset < 0 {
+            (self.past_color, self.past)
+        } else {
+            (self.future_color, self.future)
+        };
+        let distance = (offset.abs() as u32 / self.spacing.max(1)) as f32;
+        let alpha = self.opacity * (1.0 - (distance - 1.0) / count.max(1) as f32);
+        Tint(Srgba::new(color[0], color[1], color[2], alpha))
+    }
+}
+
+/// A ghost of the skinned mesh `source`, posed `offset` frames away from it.
+///
+/// Ghosts are only drawn by the onion skin pass of `RenderCustom3D`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OnionGhost {
+    pub source: Entity,
+    pub offset: i32,
+}
+
+impl Component for OnionGhost {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Creates, poses and removes the `OnionGhost`s of `OnionSkin::target`.
+///
+/// Ghost poses are sampled from the clips currently running on the model, so they follow
+/// playback as well as scrubbing with a `ManualClock`.
+#[derive(Debug, Default)]
+pub struct OnionSkinSystem;
+
+impl<'a> System<'a> for OnionSkinSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, OnionSkin>,
+        Read<'a, AssetStorage<Sampler<SamplerPrimitive<f32>>>>,
+        ReadStorage<'a, AnimationHierarchy<Transform>>,
+        ReadStorage<'a, SamplerControlSet<Transform>>,
+        ReadStorage<'a, Skin>,
+        ReadStorage<'a, Parent>,
+        WriteStorage<'a, OnionGhost>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Handle<Mesh>>,
+        WriteStorage<'a, Handle<Material>>,
+        WriteStorage<'a, JointTransforms>,
+        WriteStorage<'a, Tint>,
+        WriteStorage<'a, Hidden>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            onion,
+            samplers,
+            hierarchies,
+            sampler_sets,
+            skins,
+            parents,
+            mut ghosts,
+            mut transforms,
+            mut meshes,
+            mut materials,
+            mut joints,
+            mut tints,
+            mut hidden,
+        ): Self::SystemData,
+    ) {
+        let hierarchy = onion
+            .target
+            .filter(|_| onion.enabled)
+            .and_then(|target| hierarchies.get(target));
+        let hierarchy = match hierarchy {
+            Some(hierarchy) => hierarchy,
+            None => {
+                for (entity, _) in (&*entities, &ghosts).join() {
+                    let _ = entities.delete(entity);
+                }
+                return;
+            }
+        };
+
+        // Every sampler currently driving a node, with the time it is at.
+        let running = hierarchy
+            .nodes
+            .values()
+            .filter_map(|&node| sampler_sets.get(node).map(|set| (node, set)))
+            .flat_map(|(node, set)| {
+                set.samplers
+                    .iter()
+                    .filter_map(move |control| match control.state {
+                        ControlState::Running(time) => Some((node, control, time)),
+                        _ => None,
+                    })
+            })
+            .filter_map(|(node, control, time)| {
+                samplers
+                    .get(&control.sampler)
+                    .map(|sampler| (node, control, sampler, time.as_secs_f32()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut wanted = Vec::new();
+        for offset in onion.offsets() {
+            let delta = offset as f32 / onion.frame_rate;
+            // Clips being cross-faded drive the same channels, so their samples are blended
+            // by weight the way the sampling system does.
+            let mut blended = Vec::new();
+            for (node, control, sampler, time) in &running {
+                let looping = matches!(control.end, EndControl::Loop(_));
+                let value = sampler.function.interpolate(
+                    sample_time(&sampler.input, time + delta, looping),
+                    &sampler.input,
+                    &sampler.output,
+                    false,
+                );
+                let weight = control.blend_weight;
+                match blended
+                    .iter_mut()
+                    .find(|(n, channel, _, _)| n == node && *channel == control.channel)
+                {
+                    Some((_, _, total, sum)) => {
+                        *total += weight;
+                        *sum = sum.add(&value.mul(weight));
+                    }
+                    None => {
+                        blended.push((*node, control.channel.clone(), weight, value.mul(weight)))
+                    }
+                }
+            }
+            let mut locals = HashMap::new();
+            for (node, channel, total, sum) in blended {
+                if total <= 0.0 {
+                    continue;
+                }
+                locals
+                    .entry(node)
+                    .or_insert_with(|| transforms.get(node).cloned().unwrap_or_default())
+                    .apply_sample(&channel, &sum.mul(1.0 / total), &());
+            }
+
+            let mut globals = HashMap::new();
+            for skin in (&skins).join() {
+                if !skin
+                    .joints
+                    .iter()
+                    .all(|joint| hierarchy.nodes.values().any(|n| n == joint))
+                {
+                    continue;
+                }
+                let matrices = skin
+                    .joints
+                    .iter()
+                    .zip(&skin.inverse_bind_matrices)
+                    .map(|(&joint, inverse_bind)| {
+                        ghost_global(joint, &locals, &transforms, &parents, &mut globals)
+                            * inverse_bind
+                            * skin.bind_shape_matrix
+                    })
+                    .collect::<Vec<_>>();
+                for &mesh in &skin.meshes {
+                    let ghost = OnionGhost {
+                        source: mesh,
+                        offset,
+                    };
+                    wanted.push((ghost, matrices.clone()));
+                }
+            }
+        }
+
+        // Keep ghosts that are still wanted, so they are not recreated every frame.
+        for (entity, ghost) in (&*entities, &ghosts).join() {
+            match wanted.iter().position(|(wanted, _)| wanted == ghost) {
+                Some(index) => {
+                    let (_, matrices) = wanted.swap_remove(index);
+                    let _ = joints.insert(entity, ghost_joints(entity, matrices));
+                    let _ = tints.insert(entity, onion.tint(ghost.offset));
+                }
+                None => {
+                    let _ = entities.delete(entity);
+                }
+            }
+        }
+        for (ghost, matrices) in wanted {
+            let (mesh, material) = match (meshes.get(ghost.source), materials.get(ghost.source)) {
+                (Some(mesh), Some(material)) => (mesh.clone(), material.clone()),
+                _ => continue,
+            };
+            // Joint matrices are in world space, so the ghost itself sits at the origin.
+            let entity = entities.create();
+            let _ = transforms.insert(entity, Transform::default());
+            let _ = meshes.insert(entity, mesh);
+            let _ = materials.insert(entity, material);
+            let _ = joints.insert(entity, ghost_joints(entity, matrices));
+            let _ = tints.insert(entity, onion.tint(ghost.offset));
+            // Only the onion pass draws ghosts, the visibility-sorted passes would render
+            // them as opaque copies of the model.
+            let _ = hidden.insert(entity, Hidden);
+            let _ = ghosts.insert(entity, ghost);
+        }
+    }
+}
+
+/// Time to sample a clip's `input` at, `time` seconds into its playback.
+///
+/// Looping clips wrap around at their own last key frame like the sampling system does;
+/// others hold their first or last pose.
+fn sample_time(input: &[f32], time: f32, looping: bool) -> f32 {
+    let first = input.first().cloned().unwrap_or(0.0);
+    let last = input.last().cloned().unwrap_or(0.0);
+    let time = if looping && last > 0.0 {
+        time.rem_euclid(last)
+    } else {
+        time
+    };
+    time.max(first).min(last)
+}
+
+/// Joint transforms of a ghost, keyed by the ghost itself so that the skinning pass does
+/// not share them with the skin being ghosted.
+fn ghost_joints(ghost: Entity, matrices: Vec<Matrix4<f32>>) -> JointTransforms {
+    JointTransforms {
+        skin: ghost,
+        size: matrices.len(),
+        matrices,
+    }
+}
+
+/// Global matrix of `entity` with the ghost's local transforms substituted for its own.
+fn ghost_global(
+    entity: Entity,
+    locals: &HashMap<Entity, Transform>,
+    transforms: &WriteStorage<'_, Transform>,
+    parents: &ReadStorage<'_, Parent>,
+    globals: &mut HashMap<Entity, Matrix4<f32>>,
+) -> Matrix4<f32> {
+    if let Some(global) = globals.get(&entity) {
+        return *global;
+    }
+    let global = match locals.get(&entity) {
+        // Not animated: only its ancestors may have moved.
+        None => match transforms.get(entity) {
+            Some(transform) => match parents.get(entity) {
+                Some(parent) => {
+                    ghost_global(parent.entity, locals, transforms, parents, globals)
+                        * transform.matrix()
+                }
+                None => *transform.global_matrix(),
+            },
+            None => Matrix4::identity(),
+        },
+        Some(local) => match parents.get(entity) {
+            Some(parent) => {
+                ghost_global(parent.entity, locals, transforms, parents, globals) * local.matrix()
+            }
+            None => local.matrix(),
+        },
+    };
+    globals.insert(entity, global);
+    global
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looping_clips_wrap_at_their_own_length() {
+        let input = [0.0, 0.5, 1.0];
+        assert_eq!(sample_time(&input, 1.25, true), 0.25);
+        assert_eq!(sample_time(&input, -0.25, true), 0.75);
+        // A longer clip playing alongside does not stretch this one.
+        assert_eq!(sample_time(&[0.0, 2.0], 1.25, true), 1.25);
+    }
+
+    #[test]
+    fn other_clips_hold_their_end_poses() {
+        let input = [0.25, 1.0];
+        assert_eq!(sample_time(&input, 1.25, false), 1.0);
+        assert_eq!(sample_time(&input, -0.5, false), 0.25);
+        assert_eq!(sample_time(&input, 0.5, false), 0.5);
+    }
+}
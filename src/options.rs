@@ -0,0 +1,314 @@
+use amethyst::Error;
+
+use crate::style::PRESET_DIR;
+
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+Usage: npr_app [OPTIONS]
+
+Options:
+    --model PATH          glTF, GLB or OBJ model to show, relative to assets/
+    --scene PATH          Scene prefab to load, relative to assets/ (default prefabs/model_animation.ron)
+    --size WIDTHxHEIGHT   Window size in pixels (default from config/display.ron)
+    --style STYLE         Shading style: cel, shaded or pbr (default from config/render.ron)
+    --preset NAME         Style preset from assets/styles/ to start with
+    --anim NAME           Animation to start, by name or index
+    --time SECONDS        Pose the starting animation at this time instead of playing it
+    --camera-path PATH    Play a camera path, relative to assets/, once loaded
+    --export-svg PATH     Write the line drawing to PATH once loaded, then quit
+    --help                Print this message";
+
+/// Which render plugin shades the models.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShadingStyle {
+    /// Cel shading with outlines, drawn by `RenderCustom3D`.
+    Cel,
+    /// Amethyst's Phong shading.
+    Shaded,
+    /// Amethyst's physically based shading.
+    Pbr,
+}
+
+impl Default for ShadingStyle {
+    fn default() -> Self {
+        ShadingStyle::Cel
+    }
+}
+
+impl ShadingStyle {
+    pub fn parse(style: &str) -> Result<Self, Error> {
+        match style.to_ascii_lowercase().as_str() {
+            "cel" => Ok(ShadingStyle::Cel),
+            "shaded" => Ok(ShadingStyle::Shaded),
+            "pbr" => Ok(ShadingStyle::Pbr),
+            _ => Err(Error::from_string(format!(
+                "unknown shading style `{}`, expected cel, shaded or pbr",
+                style
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShadingStyle::Cel => "cel",
+            ShadingStyle::Shaded => "shaded",
+            ShadingStyle::Pbr => "pbr",
+        }
+    }
+}
+
+/// File formats a model can be loaded from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelFormat {
+    Gltf,
+    Glb,
+    Obj,
+}
+
+impl ModelFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gltf" => Some(ModelFormat::Gltf),
+            "glb" => Some(ModelFormat::Glb),
+            "obj" => Some(ModelFormat::Obj),
+            _ => None,
+        }
+    }
+}
+
+/// What to load at startup: either a single model or a scene prefab.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneSource {
+    Model { path: String, format: ModelFormat },
+    Prefab(String),
+}
+
+impl Default for SceneSource {
+    fn default() -> Self {
+        SceneSource::Prefab("prefabs/model_animation.ron".to_string())
+    }
+}
+
+/// Command-line options, validated against the assets directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub scene: SceneSource,
+    /// Overrides the window size of `config/display.ron`.
+    pub window_size: Option<(u32, u32)>,
+    /// Overrides the default style of `config/render.ron`.
+    pub style: Option<ShadingStyle>,
+    /// Style preset to start with, by file name without extension.
+    pub preset: Option<String>,
+    /// Clip to start once loaded, by name or index.
+    pub animation: Option<String>,
+    /// Pose the starting clip at this time instead of playing it.
+    pub time: Option<f32>,
+    /// Camera path to play once loaded, relative to the assets directory.
+    pub camera_path: Option<String>,
+    /// Export the line drawing to this path once loaded, then quit.
+    pub svg_export: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            scene: SceneSource::default(),
+            window_size: None,
+            style: None,
+            preset: None,
+            animation: None,
+            time: None,
+            camera_path: None,
+            svg_export: None,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    /// Parses `args`, without the program name, checking that referenced assets exist.
+    pub fn parse<I>(args: I, assets_dir: &Path) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Options::default();
+        let (mut model, mut scene) = (None, None);
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| Error::from_string(format!("{} expects a value", arg)))
+            };
+            match arg.as_str() {
+                "--model" => model = Some(value()?),
+                "--scene" => scene = Some(value()?),
+                "--size" => options.window_size = Some(parse_size(&value()?)?),
+                "--style" => options.style = Some(ShadingStyle::parse(&value()?)?),
+                "--preset" => {
+                    let preset = value()?;
+                    check_asset(assets_dir, &format!("{}/{}.ron", PRESET_DIR, preset))?;
+                    options.preset = Some(preset);
+                }
+                "--anim" => options.animation = Some(value()?),
+                "--time" => {
+                    let time = value()?;
+                    options.time = Some(time.parse().ok().filter(|t: &f32| *t >= 0.0).ok_or_else(
+                        || Error::from_string(format!("--time expects seconds, got `{}`", time)),
+                    )?);
+                }
+                "--camera-path" => {
+                    let path = value()?;
+                    check_asset(assets_dir, &path)?;
+                    options.camera_path = Some(path);
+                }
+                "--export-svg" => options.svg_export = Some(PathBuf::from(value()?)),
+                "--help" | "-h" => options.help = true,
+                _ => {
+                    return Err(Error::from_string(format!(
+                        "unknown argument `{}`, see --help",
+                        arg
+                    )))
+                }
+            }
+        }
+
+        options.scene = match (model, scene) {
+            (Some(_), Some(_)) => {
+                return Err(Error::from_string("--model and --scene cannot be used together"))
+            }
+            (Some(path), None) => {
+                let format = ModelFormat::from_path(Path::new(&path)).ok_or_else(|| {
+                    Error::from_string(format!(
+                        "unsupported model `{}`, expected a .gltf, .glb or .obj file",
+                        path
+                    ))
+                })?;
+                check_asset(assets_dir, &path)?;
+                SceneSource::Model { path, format }
+            }
+            (None, Some(path)) => {
+                check_asset(assets_dir, &path)?;
+                SceneSource::Prefab(path)
+            }
+            (None, None) => SceneSource::default(),
+        };
+        Ok(options)
+    }
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), Error> {
+    let error = || {
+        Error::from_string(format!(
+            "--size expects WIDTHxHEIGHT, e.g. 1280x720, got `{}`",
+            size
+        ))
+    };
+    let mut parts = size.splitn(2, |c| c == 'x' || c == 'X');
+    let width = parts.next().and_then(|w| w.parse().ok()).ok_or_else(error)?;
+    let height = parts.next().and_then(|h| h.parse().ok()).ok_or_else(error)?;
+    if width == 0 || height == 0 {
+        return Err(error());
+    }
+    Ok((width, height))
+}
+
+fn check_asset(assets_dir: &Path, path: &str) -> Result<(), Error> {
+    if assets_dir.join(path).is_file() {
+        Ok(())
+    } else {
+        Err(Error::from_string(format!(
+            "`{}` not found in {}",
+            path,
+            assets_dir.display()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, Error> {
+        let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        Options::parse(args.iter().map(|arg| arg.to_string()), &assets_dir)
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let options = parse(&[
+            "--model",
+            "fox/scene.gltf",
+            "--size",
+            "1280x720",
+            "--style",
+            "PBR",
+            "--anim",
+            "Run",
+            "--time",
+            "1.25",
+            "--export-svg",
+            "out.svg",
+        ])
+        .unwrap();
+        assert_eq!(
+            options.scene,
+            SceneSource::Model {
+                path: "fox/scene.gltf".to_string(),
+                format: ModelFormat::Gltf,
+            }
+        );
+        assert_eq!(options.window_size, Some((1280, 720)));
+        assert_eq!(options.style, Some(ShadingStyle::Pbr));
+        assert_eq!(options.animation.as_deref(), Some("Run"));
+        assert_eq!(options.time, Some(1.25));
+        assert_eq!(options.svg_export, Some(PathBuf::from("out.svg")));
+    }
+
+    #[test]
+    fn rejects_empty_size() {
+        assert!(parse(&["--size", "0x10"]).is_err());
+        assert!(parse(&["--size", "640"]).is_err());
+        assert_eq!(
+            parse(&["--size", "640X480"]).unwrap().window_size,
+            Some((640, 480))
+        );
+    }
+
+    #[test]
+    fn rejects_missing_value() {
+        let error = parse(&["--anim"]).unwrap_err();
+        assert_eq!(error.to_string(), "--anim expects a value");
+    }
+
+    #[test]
+    fn rejects_unknown_flag() {
+        let error = parse(&["--fullscreen"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown argument `--fullscreen`, see --help"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_style() {
+        assert!(parse(&["--style", "watercolor"]).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_model() {
+        let error = parse(&["--model", "missing/model.gltf"]).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("`missing/model.gltf` not found"));
+        assert!(parse(&["--model", "fox/scene.fbx"]).is_err());
+        assert!(parse(&["--model", "fox/scene.gltf", "--scene", "prefabs/scene.ron"]).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use amethyst::{
+    config::Config, core::frame_limiter::FrameRateLimitStrategy, window::DisplayConfig, Error,
+    LogLevelFilter,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::options::ShadingStyle;
+
+use std::{path::Path, time::Duration};
+
+/// MSAA sample counts the render target can be created with.
+pub const MSAA_SAMPLES: [u8; 5] = [1, 2, 4, 8, 16];
+
+/// Rendering and startup settings read from `config/render.ron`.
+///
+/// Every field is optional in the file; missing ones keep their defaults.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    /// Linear RGBA color the window is cleared to.
+    pub clear_color: [f32; 4],
+    /// One of off, error, warn, info, debug or trace.
+    pub log_level: String,
+    /// Sleeps between frames to stay under `max_fps` instead of rendering as fast as possible.
+    pub limit_fps: bool,
+    pub max_fps: u32,
+    /// Samples per pixel of the main render target, 1 to disable MSAA.
+    pub msaa_samples: u8,
+    /// Always run FXAA, not only for style presets that list it.
+    pub fxaa: bool,
+    /// Shading style used when `--style` is not given: cel, shaded or pbr.
+    pub default_style: String,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            clear_color: [0.1, 0.1, 0.1, 1.0],
+            log_level: "error".to_string(),
+            limit_fps: true,
+            max_fps: 60,
+            msaa_samples: 1,
+            fxaa: false,
+            default_style: "cel".to_string(),
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Reads and validates the settings at `path`, using the defaults if there is no file.
+    pub fn load_or_default(path: &Path) -> Result<Self, Error> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let settings = Self::load(path).map_err(|e| config_error(path, e))?;
+        settings.validate().map_err(|e| config_error(path, e))?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.clear_color.iter().any(|c| !(0.0..=1.0).contains(c)) {
+            return Err(Error::from_string(format!(
+                "clear_color components must be between 0 and 1, got {:?}",
+                self.clear_color
+            )));
+        }
+        if self.max_fps == 0 {
+            return Err(Error::from_string("max_fps must be non-zero"));
+        }
+        if !MSAA_SAMPLES.contains(&self.msaa_samples) {
+            return Err(Error::from_string(format!(
+                "msaa_samples must be one of {:?}, got {}",
+                MSAA_SAMPLES, self.msaa_samples
+            )));
+        }
+        self.log_level()?;
+        self.style()?;
+        Ok(())
+    }
+
+    pub fn log_level(&self) -> Result<LogLevelFilter, Error> {
+        self.log_level.parse().map_err(|_| {
+            Error::from_string(format!(
+                "unknown log_level `{}`, expected off, error, warn, info, debug or trace",
+                self.log_level
+            ))
+        })
+    }
+
+    /// Frame limit for `ApplicationBuilder::with_frame_limit`.
+    pub fn frame_limit(&self) -> (FrameRateLimitStrategy, u32) {
+        if self.limit_fps {
+            (
+                FrameRateLimitStrategy::SleepAndYield(Duration::from_millis(2)),
+                self.max_fps,
+            )
+        } else {
+            (FrameRateLimitStrategy::Unlimited, self.max_fps)
+        }
+    }
+
+    pub fn style(&self) -> Result<ShadingStyle, Error> {
+        ShadingStyle::parse(&self.default_style)
+    }
+}
+
+/// Reads the window settings at `path`, falling back to a 1024x768 window if there is no file.
+pub fn load_display_config(path: &Path) -> Result<DisplayConfig, Error> {
+    if !path.is_file() {
+        return Ok(DisplayConfig {
+            title: "NPR Demo".to_string(),
+            dimensions: Some((1024, 768)),
+            ..Default::default()
+        });
+    }
+    let config = DisplayConfig::load(path).map_err(|e| config_error(path, e))?;
+    if let Some((width, height)) = config.dimensions {
+        if width == 0 || height == 0 {
+            return Err(config_error(
+                path,
+                format!("dimensions must be non-zero, got {}x{}", width, height),
+            ));
+        }
+    }
+    Ok(config)
+}
+
+fn config_error<E: std::fmt::Display>(path: &Path, error: E) -> Error {
+    Error::from_string(format!("invalid {}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_config(name: &str, contents: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("npr_app_{}_{}.ron", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(RenderSettings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_out_of_range_clear_color() {
+        let settings = RenderSettings {
+            clear_color: [0.1, 1.5, 0.1, 1.0],
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_zero_max_fps() {
+        let settings = RenderSettings {
+            max_fps: 0,
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_msaa_samples() {
+        let settings = RenderSettings {
+            msaa_samples: 3,
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_log_level() {
+        let settings = RenderSettings {
+            log_level: "loud".to_string(),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_default_style() {
+        let settings = RenderSettings {
+            default_style: "watercolor".to_string(),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn frame_limit_follows_limit_fps() {
+        let settings = RenderSettings {
+            limit_fps: false,
+            max_fps: 144,
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.frame_limit(),
+            (FrameRateLimitStrategy::Unlimited, 144)
+        );
+        let (strategy, max_fps) = RenderSettings::default().frame_limit();
+        assert_ne!(strategy, FrameRateLimitStrategy::Unlimited);
+        assert_eq!(max_fps, 60);
+    }
+
+    #[test]
+    fn missing_settings_file_uses_defaults() {
+        let path = std::env::temp_dir().join("npr_app_no_such_render.ron");
+        assert_eq!(
+            RenderSettings::load_or_default(&path).unwrap(),
+            RenderSettings::default()
+        );
+    }
+
+    #[test]
+    fn partial_settings_file_keeps_other_defaults() {
+        let path = temp_config("render", "(max_fps: 30, default_style: \"pbr\")");
+        let settings = RenderSettings::load_or_default(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let settings = settings.unwrap();
+        assert_eq!(settings.max_fps, 30);
+        assert_eq!(settings.style().unwrap(), ShadingStyle::Pbr);
+        assert_eq!(settings.clear_color, RenderSettings::default().clear_color);
+    }
+
+    #[test]
+    fn invalid_settings_file_names_the_file() {
+        let path = temp_config("render_invalid", "(max_fps: 0)");
+        let error = RenderSettings::load_or_default(&path).unwrap_err();
+        let _ = std::fs::remove_file(&path);
+        assert!(error.to_string().contains(&path.display().to_string()));
+    }
+
+    #[test]
+    fn unknown_settings_field_is_an_error() {
+        let path = temp_config("render_unknown", "(vsync: true)");
+        let settings = RenderSettings::load_or_default(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(settings.is_err());
+    }
+
+    #[test]
+    fn missing_display_file_uses_default_window() {
+        let path = std::env::temp_dir().join("npr_app_no_such_display.ron");
+        let config = load_display_config(&path).unwrap();
+        assert_eq!(config.title, "NPR Demo");
+        assert_eq!(config.dimensions, Some((1024, 768)));
+    }
+
+    #[test]
+    fn reads_display_file() {
+        let path = temp_config("display", "(title: \"Test\", dimensions: Some((640, 480)))");
+        let config = load_display_config(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let config = config.unwrap();
+        assert_eq!(config.title, "Test");
+        assert_eq!(config.dimensions, Some((640, 480)));
+    }
+
+    #[test]
+    fn rejects_zero_display_dimensions() {
+        let path = temp_config("display_zero", "(dimensions: Some((0, 480)))");
+        let config = load_display_config(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(config.is_err());
+    }
+}
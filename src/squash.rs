@@ -0,0 +1,236 @@
+use amethyst::{
+    core::{
+        ecs::{
+            Component, DenseVecStorage, Entities, Join, Read, ReadExpect, ReadStorage, System,
+            WriteStorage,
+        },
+        math::{Point3, Vector3},
+        ParentHierarchy, Time, Transform,
+    },
+    derive::PrefabData,
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+/// Cartoon squash and stretch: stretches a moving entity along its direction of motion while
+/// keeping its volume, and optionally smears the trailing half of it behind.
+///
+/// The deformation is applied in the vertex shader of `RenderCustom3D` to the entity and all
+/// of its descendants, so it works for whole glTF scenes as well as single meshes.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+pub struct SquashStretch {
+    /// Stretch gained per world unit per second of speed.
+    pub stretch: f32,
+    /// Upper bound of the stretch factor along the motion.
+    pub max_stretch: f32,
+    /// Smear gained per world unit per second of speed, 0 to disable smears.
+    ///
+    /// The smear is a scale factor: each point of the trailing half is dragged back by its
+    /// distance behind the center times this factor, so it suits models of any size.
+    pub smear: f32,
+    /// Fraction of the previous velocity kept each frame, to steady jittery motion.
+    pub smoothing: f32,
+    #[serde(skip)]
+    previous: Option<Vector3<f32>>,
+    #[serde(skip, default = "Vector3::zeros")]
+    velocity: Vector3<f32>,
+}
+
+impl Default for SquashStretch {
+    fn default() -> Self {
+        SquashStretch {
+            stretch: 0.02,
+            max_stretch: 1.6,
+            smear: 0.0,
+            smoothing: 0.5,
+            previous: None,
+            velocity: Vector3::zeros(),
+        }
+    }
+}
+
+impl Component for SquashStretch {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl SquashStretch {
+    /// Smoothed world space velocity measured from the last frames.
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+
+    fn update(&mut self, position: Vector3<f32>, delta_seconds: f32) {
+        if let Some(previous) = self.previous {
+            if delta_seconds > 0.0 {
+                let velocity = (position - previous) / delta_seconds;
+                self.velocity = self.velocity * self.smoothing + velocity * (1.0 - self.smoothing);
+            }
+        }
+        self.previous = Some(position);
+    }
+
+    fn deformation(&self, center: Vector3<f32>) -> Deformation {
+        let speed = self.velocity.norm();
+        if speed <= std::f32::EPSILON {
+            return Deformation::default();
+        }
+        let direction = self.velocity / speed;
+        let stretch = (1.0 + self.stretch * speed).min(self.max_stretch.max(1.0));
+        Deformation {
+            motion: [direction.x, direction.y, direction.z, stretch],
+            smear: [center.x, center.y, center.z, self.smear * speed],
+        }
+    }
+}
+
+/// Per-instance squash and stretch read by the cel shading vertex shaders.
+///
+/// Written by `SquashStretchSystem` to every entity under a `SquashStretch`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Deformation {
+    /// Unit direction of motion, and stretch factor along it in `w`.
+    pub motion: [f32; 4],
+    /// World space center the entity is deformed about, and smear factor in `w`.
+    pub smear: [f32; 4],
+}
+
+impl Deformation {
+    /// Deforms a world space point, matching `deform_position` in `header/deform.vert`.
+    pub fn apply(&self, point: &Point3<f32>) -> Point3<f32> {
+        let direction = Vector3::new(self.motion[0], self.motion[1], self.motion[2]);
+        let (stretch, center) = (
+            self.motion[3],
+            Point3::new(self.smear[0], self.smear[1], self.smear[2]),
+        );
+        let relative = point - center;
+        let along = relative.dot(&direction);
+        let across = relative - direction * along;
+        let mut relative = across / stretch.sqrt() + direction * (along * stretch);
+        relative += direction * (along.min(0.0) * self.smear[3]);
+        center + relative
+    }
+}
+
+impl Default for Deformation {
+    fn default() -> Self {
+        Deformation {
+            motion: [0.0, 0.0, 0.0, 1.0],
+            smear: [0.0; 4],
+        }
+    }
+}
+
+impl Component for Deformation {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Measures the velocity of `SquashStretch` entities from their global transforms and hands
+/// the resulting `Deformation` down their hierarchy.
+#[derive(Debug, Default)]
+pub struct SquashStretchSystem;
+
+impl<'a> System<'a> for SquashStretchSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        ReadExpect<'a, ParentHierarchy>,
+        ReadStorage<'a, Transform>,
+        WriteStorage<'a, SquashStretch>,
+        WriteStorage<'a, Deformation>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, hierarchy, transforms, mut squashes, mut deformations): Self::SystemData,
+    ) {
+        deformations.clear();
+        for (entity, squash, transform) in (&*entities, &mut squashes, &transforms).join() {
+            let center = transform.global_matrix().column(3).xyz();
+            squash.update(center, time.delta_seconds());
+            let deformation = squash.deformation(center);
+            let _ = deformations.insert(entity, deformation);
+            for (child, _) in (&*entities, &hierarchy.all_children(entity)).join() {
+                let _ = deformations.insert(child, deformation);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squash() -> SquashStretch {
+        SquashStretch {
+            stretch: 0.1,
+            max_stretch: 1.5,
+            smear: 0.0,
+            smoothing: 0.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn velocity_is_smoothed_over_frames() {
+        let mut squash = squash();
+        squash.update(Vector3::zeros(), 0.5);
+        assert_eq!(squash.velocity(), Vector3::zeros());
+        // Moving at 2 units per second only reaches half of that in the first frame.
+        squash.update(Vector3::new(1.0, 0.0, 0.0), 0.5);
+        assert_eq!(squash.velocity(), Vector3::new(1.0, 0.0, 0.0));
+        squash.update(Vector3::new(2.0, 0.0, 0.0), 0.5);
+        assert_eq!(squash.velocity(), Vector3::new(1.5, 0.0, 0.0));
+        // Frames without time passing keep the velocity.
+        squash.update(Vector3::new(2.0, 0.0, 0.0), 0.0);
+        assert_eq!(squash.velocity(), Vector3::new(1.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn stretch_is_clamped_at_max_stretch() {
+        let mut squash = squash();
+        squash.velocity = Vector3::new(0.0, 2.0, 0.0);
+        let deformation = squash.deformation(Vector3::zeros());
+        assert_eq!(deformation.motion, [0.0, 1.0, 0.0, 1.2]);
+
+        squash.velocity = Vector3::new(0.0, 100.0, 0.0);
+        assert_eq!(squash.deformation(Vector3::zeros()).motion[3], 1.5);
+
+        squash.velocity = Vector3::zeros();
+        assert_eq!(squash.deformation(Vector3::zeros()), Deformation::default());
+    }
+
+    #[test]
+    fn stretch_preserves_volume() {
+        let mut squash = squash();
+        squash.velocity = Vector3::new(0.0, 0.0, 4.0);
+        let center = Vector3::new(1.0, 2.0, 3.0);
+        let deformation = squash.deformation(center);
+        let scale = |axis: Vector3<f32>| {
+            let moved = deformation.apply(&Point3::from(center + axis));
+            (moved - Point3::from(center)).norm()
+        };
+        let along = scale(Vector3::z());
+        let across = scale(Vector3::x());
+        assert!((along - 1.4).abs() < 1.0e-5);
+        assert!((scale(Vector3::y()) - across).abs() < 1.0e-5);
+        assert!((across * across * along - 1.0).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn smear_drags_the_trailing_half() {
+        let mut squash = SquashStretch {
+            smear: 0.25,
+            ..squash()
+        };
+        squash.velocity = Vector3::new(2.0, 0.0, 0.0);
+        let deformation = squash.deformation(Vector3::zeros());
+        // The factor is smear times speed, regardless of the model's size.
+        assert_eq!(deformation.smear[3], 0.5);
+        let leading = deformation.apply(&Point3::new(1.0, 0.0, 0.0));
+        let trailing = deformation.apply(&Point3::new(-1.0, 0.0, 0.0));
+        assert!((leading.x - 1.2).abs() < 1.0e-5);
+        assert!((trailing.x + 1.7).abs() < 1.0e-5);
+    }
+}
@@ -0,0 +1,806 @@
+use amethyst::{
+    animation::Skin,
+    assets::{lazy_static, AssetLoaderSystemData, Handle},
+    core::{
+        ecs::{
+            Component, DenseVecStorage, DispatcherBuilder, Entities, Entity, Join, Read,
+            ReadExpect, ReadStorage, System, SystemData, World, Write, WriteStorage,
+        },
+        math::{Matrix4, Point3, Vector3},
+        ParentHierarchy, Transform,
+    },
+    renderer::{
+        bundle::{RenderOrder, RenderPlan, RenderPlugin, Target},
+        camera::{ActiveCamera, Camera, Projection},
+        loaders::load_from_srgba,
+        palette::Srgba,
+        pipeline::{PipelineDescBuilder, PipelinesBuilder},
+        rendy::{
+            command::{QueueId, RenderPassEncoder},
+            factory::Factory,
+            graph::{
+                render::{PrepareResult, RenderGroup, RenderGroupDesc},
+                GraphContext, NodeBuffer, NodeImage,
+            },
+            hal::{self, device::Device, format::Format, pso, pso::ShaderStageFlags},
+            mesh::{AsVertex, VertexFormat},
+            shader::{Shader, SpirvShader},
+        },
+        submodules::{DynamicUniform, DynamicVertexBuffer, TextureId, TextureSub},
+        types::{Backend, Texture},
+        util, ChangeDetection, ImageFormat,
+    },
+    Error,
+};
+use derivative::*;
+use glsl_layout::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    antialiasing::multisampling,
+    boil::{hash, value_noise, LineBoil},
+    lines::{
+        chain_edges, EdgeKind, LineMesh, LineMeshCache, LineSettings, LineSource, Viewpoint,
+    },
+    squash::Deformation,
+};
+
+use std::collections::HashSet;
+
+lazy_static::lazy_static! {
+    // These uses the precompiled shaders.
+    // These can be obtained using glslc.exe in the vulkan sdk.
+    static ref VERTEX: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../assets/shaders/stroke.vert.spv"),
+        ShaderStageFlags::VERTEX,
+        "main",
+    ).unwrap();
+
+    static ref FRAGMENT: SpirvShader = SpirvShader::from_bytes(
+        include_bytes!("../assets/shaders/stroke.frag.spv"),
+        ShaderStageFlags::FRAGMENT,
+        "main",
+    ).unwrap();
+}
+
+/// A world-space polyline to be drawn as a single brush stroke.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokePath {
+    pub kind: EdgeKind,
+    pub points: Vec<Point3<f32>>,
+    pub closed: bool,
+}
+
+/// The strokes an entity contributes to `DrawStrokes` this frame.
+#[derive(Clone, Debug, Default)]
+pub struct Strokes {
+    pub paths: Vec<StrokePath>,
+}
+
+impl Component for Strokes {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Appearance of every stroke drawn by `RenderStrokes`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StrokeStyle {
+    /// Stroke width in world units at full pressure.
+    pub width: f32,
+    /// Width multiplier applied to creases and boundaries relative to silhouettes.
+    pub inner_line_scale: f32,
+    /// Fraction of the stroke length, at each end, over which the width tapers to zero.
+    pub taper: f32,
+    /// How much the width varies along the stroke, from 0 (constant) to 1.
+    pub width_variation: f32,
+    /// Length in world units over which the width variation changes.
+    pub variation_wavelength: f32,
+    /// Maximum random per-vertex displacement in world units.
+    pub jitter: f32,
+    /// World-space length covered by one repeat of the brush texture.
+    pub brush_length: f32,
+    pub color: [f32; 4],
+    /// Brush texture path relative to the assets directory, a plain solid brush if `None`.
+    pub brush: Option<String>,
+    pub seed: u32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            width: 0.08,
+            inner_line_scale: 0.6,
+            taper: 0.15,
+            width_variation: 0.3,
+            variation_wavelength: 1.5,
+            jitter: 0.01,
+            brush_length: 1.0,
+            color: [0.0, 0.0, 0.0, 1.0],
+            brush: None,
+            seed: 0,
+        }
+    }
+}
+
+/// Brush texture sampled by `DrawStrokes`.
+#[derive(Clone, Debug)]
+pub struct StrokeBrush(pub Handle<Texture>);
+
+/// Vertex Arguments to pass into shader.
+/// VertexData in shader:
+/// layout(location = 0) out VertexData {
+///    vec2 tex_coord;
+///    vec4 color;
+/// } vertex;
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, AsStd140)]
+#[repr(C, align(4))]
+pub struct StrokeVertex {
+    /// vec3 pos;
+    pub pos: vec3,
+    /// vec2 tex_coord;
+    pub tex_coord: vec2,
+    /// vec4 color;
+    pub color: vec4,
+}
+
+/// Required to send data into the shader.
+/// These names must match the shader.
+impl AsVertex for StrokeVertex {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            // vec3 pos;
+            (Format::Rgb32Sfloat, "pos"),
+            // vec2 tex_coord;
+            (Format::Rg32Sfloat, "tex_coord"),
+            // vec4 color;
+            (Format::Rgba32Sfloat, "color"),
+        ))
+    }
+}
+
+/// StrokeUniformArgs
+/// A Uniform we pass into the shader containing the camera's view-projection matrix.
+/// Uniform in shader:
+/// layout(std140, set = 0, binding = 0) uniform StrokeUniformArgs {
+///    uniform mat4 proj_view;
+/// };
+#[derive(Clone, Copy, Debug, AsStd140)]
+#[repr(C, align(4))]
+pub struct StrokeUniformArgs {
+    pub proj_view: mat4,
+}
+
+/// Width profile that ramps from zero to one over `taper` at both ends of the stroke.
+fn taper(t: f32, taper: f32) -> f32 {
+    if taper <= 0.0 {
+        return 1.0;
+    }
+    let edge = (t.min(1.0 - t) / taper).min(1.0).max(0.0);
+    edge * edge * (3.0 - 2.0 * edge)
+}
+
+/// Expands `path` into camera-facing quads, appending two triangles per segment to `out`.
+///
+/// Widths are in world units under either projection; orthographic cameras only change which
+/// way the quads face.
+pub fn build_stroke_vertices(
+    path: &StrokePath,
+    path_id: u32,
+    viewpoint: &Viewpoint,
+    style: &StrokeStyle,
+    boil: &LineBoil,
+    out: &mut Vec<StrokeVertex>,
+) {
+    let points = &path.points;
+    if points.len() < 2 {
+        return;
+    }
+
+    let mut distances = Vec::with_capacity(points.len());
+    let mut total = 0.0;
+    for (i, point) in points.iter().enumerate() {
+        if i > 0 {
+            total += (point - points[i - 1]).norm();
+        }
+        distances.push(total);
+    }
+    if total <= std::f32::EPSILON {
+        return;
+    }
+
+    let seed = style.seed ^ path_id.wrapping_mul(0x2545_F491) ^ boil.jitter_seed();
+    let uv_offset = boil.uv_offset();
+    let base_width = match path.kind {
+        EdgeKind::Silhouette => style.width,
+        EdgeKind::Crease | EdgeKind::Boundary => style.width * style.inner_line_scale,
+    };
+    let last = points.len() - 1;
+    let sides = points
+        .iter()
+        .enumerate()
+        .map(|(i, point)| {
+            let (prev, next) = if path.closed {
+                // The first and last points of a closed chain coincide.
+                (
+                    if i == 0 { last - 1 } else { i - 1 },
+                    if i == last { 1 } else { i + 1 },
+                )
+            } else {
+                (i.saturating_sub(1), (i + 1).min(last))
+            };
+            let tangent = points[next] - points[prev];
+            let side = tangent.cross(&viewpoint.to_viewer(point));
+            let side = if side.norm_squared() > std::f32::EPSILON {
+                side.normalize()
+            } else {
+                tangent
+                    .cross(&Vector3::y())
+                    .try_normalize(std::f32::EPSILON)
+                    .unwrap_or_else(Vector3::x)
+            };
+
+            let t = distances[i] / total;
+            let profile = if path.closed { 1.0 } else { taper(t, style.taper) };
+            let pressure = 1.0
+                + style.width_variation
+                    * value_noise(seed, distances[i] / style.variation_wavelength.max(1.0e-3));
+            let half_width = 0.5 * base_width * profile * pressure.max(0.0);
+
+            // Closed loops share their first and last point, so jitter them identically.
+            let jitter_id = if path.closed && i == last { 0 } else { i as u32 };
+            let jitter = Vector3::new(
+                hash(seed, jitter_id * 3),
+                hash(seed, jitter_id * 3 + 1),
+                hash(seed, jitter_id * 3 + 2),
+            ) * style.jitter;
+
+            let center = point + jitter + boil.displacement(point);
+            let u = distances[i] / style.brush_length.max(1.0e-3) + uv_offset[0];
+            (center - side * half_width, center + side * half_width, u)
+        })
+        .collect::<Vec<_>>();
+
+    let vertex = |p: &Point3<f32>, u: f32, v: f32| StrokeVertex {
+        pos: [p.x, p.y, p.z].into(),
+        tex_coord: [u, v].into(),
+        color: style.color.into(),
+    };
+    let (v0, v1) = (uv_offset[1], 1.0 + uv_offset[1]);
+    for pair in sides.windows(2) {
+        let (l0, r0, u0) = &pair[0];
+        let (l1, r1, u1) = &pair[1];
+        out.extend_from_slice(&[
+            vertex(l0, *u0, v0),
+            vertex(r0, *u0, v1),
+            vertex(l1, *u1, v0),
+            vertex(l1, *u1, v0),
+            vertex(r0, *u0, v1),
+            vertex(r1, *u1, v1),
+        ]);
+    }
+}
+
+fn active_camera<'a>(
+    active: &ActiveCamera,
+    cameras: &'a ReadStorage<'_, Camera>,
+    transforms: &'a ReadStorage<'_, Transform>,
+) -> Option<(&'a Camera, &'a Transform)> {
+    active
+        .entity
+        .and_then(|entity| Some((cameras.get(entity)?, transforms.get(entity)?)))
+        .or_else(|| (cameras, transforms).join().next())
+}
+
+/// Re-extracts the feature lines of every `LineSource` from the active camera's point of
+/// view each frame and stores them as world-space `Strokes`.
+#[derive(Debug, Default)]
+pub struct FeatureStrokeSystem {
+    failed: HashSet<String>,
+}
+
+impl<'a> System<'a> for FeatureStrokeSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, ActiveCamera>,
+        ReadExpect<'a, ParentHierarchy>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Skin>,
+        ReadStorage<'a, LineSource>,
+        ReadStorage<'a, Deformation>,
+        Write<'a, LineMeshCache>,
+        WriteStorage<'a, Strokes>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            active,
+            hierarchy,
+            cameras,
+            transforms,
+            skins,
+            sources,
+            deformations,
+            mut meshes,
+            mut strokes,
+        ): Self::SystemData,
+    ) {
+        let (camera, camera_transform) = match active_camera(&active, &cameras, &transforms) {
+            Some(camera) => camera,
+            None => return,
+        };
+        let viewpoint = Viewpoint::from_camera_matrix(
+            camera_transform.global_matrix(),
+            matches!(camera.projection(), Projection::Orthographic(_)),
+        );
+
+        for (entity, source, transform) in (&*entities, &sources, &transforms).join() {
+            if self.failed.contains(&source.model) {
+                continue;
+            }
+            let mesh = match meshes.get(&source.model) {
+                Ok(mesh) => mesh,
+                Err(e) => {
+                    eprintln!("Could not load lines for {}: {}", source.model, e);
+                    self.failed.insert(source.model.clone());
+                    continue;
+                }
+            };
+
+            let joints = joint_matrices(mesh, entity, &hierarchy, &skins, &transforms);
+            let mut paths = stroke_paths(
+                mesh,
+                transform.global_matrix(),
+                &joints,
+                &viewpoint,
+                &source.settings,
+            );
+            // Follow the squash and stretch the cel shader gives the model's surface.
+            if let Some(deformation) = deformations.get(entity) {
+                for point in paths.iter_mut().flat_map(|path| path.points.iter_mut()) {
+                    *point = deformation.apply(point);
+                }
+            }
+            let _ = strokes.insert(entity, Strokes { paths });
+        }
+    }
+}
+
+/// Chains the feature lines of `mesh`, posed by `joints` as in `LineMesh::posed`, into world
+/// space stroke paths.
+///
+/// Unskinned meshes are extracted in model space and moved by `global` afterwards, which
+/// saves rebuilding their normals every frame.
+pub fn stroke_paths(
+    mesh: &LineMesh,
+    global: &Matrix4<f32>,
+    joints: &[Option<Vec<Matrix4<f32>>>],
+    viewpoint: &Viewpoint,
+    settings: &LineSettings,
+) -> Vec<StrokePath> {
+    let (edges, to_world) = if mesh.skins().is_empty() {
+        let local_viewpoint =
+            viewpoint.transformed(&global.try_inverse().unwrap_or_else(Matrix4::identity));
+        (mesh.extract(&local_viewpoint, settings), *global)
+    } else {
+        (mesh.posed(global, joints).extract(viewpoint, settings), Matrix4::identity())
+    };
+    chain_edges(&edges)
+        .into_iter()
+        .map(|chain| StrokePath {
+            kind: chain.kind,
+            points: chain.points.iter().map(|p| to_world.transform_point(p)).collect(),
+            closed: chain.closed,
+        })
+        .collect()
+}
+
+/// Skinning matrices of every skin of `mesh`, ready for `LineMesh::posed`: the global matrix
+/// of each joint times its inverse bind and bind shape matrices.
+///
+/// Skins are told apart by their inverse bind matrices, which the glTF loader copies
+/// unchanged from the file the lines were read from. Skins not found among the children of
+/// `entity` are left in their bind pose.
+pub fn joint_matrices(
+    mesh: &LineMesh,
+    entity: Entity,
+    hierarchy: &ParentHierarchy,
+    skins: &ReadStorage<'_, Skin>,
+    transforms: &ReadStorage<'_, Transform>,
+) -> Vec<Option<Vec<Matrix4<f32>>>> {
+    if mesh.skins().is_empty() {
+        return Vec::new();
+    }
+    let children = hierarchy.all_children(entity);
+    mesh.skins()
+        .iter()
+        .map(|inverse_binds| {
+            let skin = (skins, &children)
+                .join()
+                .map(|(skin, _)| skin)
+                .find(|skin| same_matrices(&skin.inverse_bind_matrices, inverse_binds))?;
+            skin.joints
+                .iter()
+                .zip(&skin.inverse_bind_matrices)
+                .map(|(&joint, inverse_bind)| {
+                    let joint = transforms.get(joint)?.global_matrix();
+                    Some(joint * inverse_bind * skin.bind_shape_matrix)
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect()
+}
+
+fn same_matrices(a: &[Matrix4<f32>], b: &[Matrix4<f32>]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() <= 1.0e-4))
+}
+
+/// Draw brush strokes.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawStrokesDesc {
+    msaa_samples: u8,
+}
+
+impl DrawStrokesDesc {
+    /// Create instance of `DrawStrokesDesc` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Match the sample count of a multisampled target.
+    pub fn with_msaa_samples(mut self, samples: u8) -> Self {
+        self.msaa_samples = samples;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawStrokesDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _world: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        let env = DynamicUniform::new(factory, pso::ShaderStageFlags::VERTEX)?;
+        let textures = TextureSub::new(factory)?;
+        let vertex = DynamicVertexBuffer::new();
+
+        let (pipeline, pipeline_layout) = build_stroke_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.msaa_samples,
+            vec![env.raw_layout(), textures.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawStrokes::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            textures,
+            brush: None,
+            vertex,
+            vertex_count: 0,
+            vertices: Vec::new(),
+            change: Default::default(),
+        }))
+    }
+}
+
+/// Draws `Strokes` as textured, camera-facing quads.
+#[derive(Debug)]
+pub struct DrawStrokes<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: DynamicUniform<B, StrokeUniformArgs>,
+    textures: TextureSub<B>,
+    brush: Option<TextureId>,
+    vertex: DynamicVertexBuffer<B, StrokeVertex>,
+    vertex_count: usize,
+    // Reused between frames to avoid reallocating the expanded quads
+    vertices: Vec<StrokeVertex>,
+    change: ChangeDetection,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawStrokes<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        let (active, cameras, transforms, strokes, style, boil, brush) = <(
+            Read<'_, ActiveCamera>,
+            ReadStorage<'_, Camera>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Strokes>,
+            Read<'_, StrokeStyle>,
+            Read<'_, LineBoil>,
+            Option<Read<'_, StrokeBrush>>,
+        )>::fetch(world);
+
+        self.textures.maintain(factory, world);
+        let mut changed = false;
+        if let Some(brush) = brush {
+            if let Some((id, brush_changed)) = self.textures.insert(
+                factory,
+                world,
+                &brush.0,
+                hal::image::Layout::ShaderReadOnlyOptimal,
+            ) {
+                changed |= brush_changed || self.brush != Some(id);
+                self.brush = Some(id);
+            }
+        }
+
+        let old_vertex_count = self.vertex_count;
+        self.vertices.clear();
+        if let Some((camera, camera_transform)) = active_camera(&active, &cameras, &transforms) {
+            let global = camera_transform.global_matrix();
+            let view = global.try_inverse().unwrap_or_else(Matrix4::identity);
+            let proj_view: [[f32; 4]; 4] = (camera.as_matrix() * view).into();
+            self.env.write(
+                factory,
+                index,
+                StrokeUniformArgs {
+                    proj_view: proj_view.into(),
+                }
+                .std140(),
+            );
+
+            let viewpoint = Viewpoint::from_camera_matrix(
+                global,
+                matches!(camera.projection(), Projection::Orthographic(_)),
+            );
+            let mut path_id = 0;
+            for strokes in strokes.join() {
+                for path in &strokes.paths {
+                    build_stroke_vertices(
+                        path,
+                        path_id,
+                        &viewpoint,
+                        &style,
+                        &boil,
+                        &mut self.vertices,
+                    );
+                    path_id += 1;
+                }
+            }
+        }
+        self.vertex_count = self.vertices.len();
+        changed |= old_vertex_count != self.vertex_count;
+
+        self.vertex.write(
+            factory,
+            index,
+            self.vertex_count as u64,
+            Some(&self.vertices[..]),
+        );
+
+        self.change.prepare_result(index, changed)
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        let brush = match self.brush {
+            Some(brush) if self.vertex_count > 0 && self.textures.loaded(brush) => brush,
+            _ => return,
+        };
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+        self.textures
+            .bind(&self.pipeline_layout, 1, brush, &mut encoder);
+        self.vertex.bind(index, 0, 0, &mut encoder);
+
+        unsafe {
+            encoder.draw(0..self.vertex_count as u32, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_stroke_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    msaa_samples: u8,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let shader_vertex = unsafe { VERTEX.module(factory).unwrap() };
+    let shader_fragment = unsafe { FRAGMENT.module(factory).unwrap() };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[(StrokeVertex::vertex(), pso::VertexInputRate::Vertex)])
+                .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleList))
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_multisampling(multisampling(msaa_samples))
+                // Strokes are double sided and sit on the surface they outline, so they are
+                // depth tested against the scene without writing depth themselves.
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::LessEqual,
+                    write: false,
+                })
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::ALPHA),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
+
+/// A `RenderPlugin` that draws the feature lines of `LineSource` entities as brush strokes.
+#[derive(Default, Debug)]
+pub struct RenderStrokes {
+    style: StrokeStyle,
+    target: Target,
+    msaa_samples: u8,
+}
+
+impl RenderStrokes {
+    /// Set target to which strokes will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set the samples per pixel of the target, which must match how it was defined.
+    pub fn with_msaa_samples(mut self, samples: u8) -> Self {
+        self.msaa_samples = samples;
+        self
+    }
+
+    /// Set the appearance of the strokes.
+    pub fn with_style(mut self, style: StrokeStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderStrokes {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(FeatureStrokeSystem::default(), "feature_strokes", &[]);
+        world.register::<Strokes>();
+
+        let style = &self.style;
+        let brush = world.exec(|loader: AssetLoaderSystemData<'_, Texture>| match &style.brush {
+            Some(path) => loader.load(path.as_str(), ImageFormat::default(), ()),
+            None => loader.load_from_data(
+                load_from_srgba(Srgba::new(1.0, 1.0, 1.0, 1.0)).into(),
+                (),
+            ),
+        });
+        world.insert(StrokeBrush(brush));
+        world.insert(self.style.clone());
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let samples = self.msaa_samples;
+        plan.extend_target(self.target, move |ctx| {
+            ctx.add(
+                RenderOrder::Transparent,
+                DrawStrokesDesc::new().with_msaa_samples(samples).builder(),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lines::JointWeights;
+
+    #[test]
+    fn posed_skin_moves_its_strokes() {
+        // A quad whose bottom edge follows joint 0 and top edge joint 1.
+        let weight = |joint| {
+            Some(JointWeights {
+                skin: 0,
+                joints: [joint, 0, 0, 0],
+                weights: [1.0, 0.0, 0.0, 0.0],
+            })
+        };
+        let mesh = LineMesh::new_skinned(
+            &[
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            &[0, 1, 2, 0, 2, 3],
+            &[weight(0), weight(0), weight(1), weight(1)],
+            vec![vec![Matrix4::identity(); 2]],
+        );
+        let viewpoint = Viewpoint::Perspective(Point3::new(0.5, 0.5, 5.0));
+        let settings = LineSettings::default();
+        let global = Matrix4::identity();
+        let corners = |paths: &[StrokePath]| {
+            paths
+                .iter()
+                .flat_map(|path| path.points.iter().map(|p| [p.x, p.y, p.z]))
+                .collect::<Vec<_>>()
+        };
+
+        let bind = stroke_paths(&mesh, &global, &[None], &viewpoint, &settings);
+        assert_eq!(bind.len(), 1);
+        assert!(corners(&bind).iter().all(|p| p[2] == 0.0));
+
+        let lifted = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 1.0));
+        let joints = [Some(vec![Matrix4::identity(), lifted])];
+        let posed = stroke_paths(&mesh, &global, &joints, &viewpoint, &settings);
+        assert_ne!(posed, bind);
+        let posed_corners = corners(&posed);
+        assert!(posed_corners.contains(&[1.0, 1.0, 1.0]));
+        assert!(posed_corners.contains(&[1.0, 0.0, 0.0]));
+    }
+}
@@ -0,0 +1,512 @@
+use amethyst::{
+    assets::{Asset, AssetLoaderSystemData, AssetStorage, Handle, Loader, RonFormat},
+    core::{
+        ecs::{
+            Component, DenseVecStorage, Entities, Join, Read, ReadExpect, ReadStorage, System,
+            VecStorage, Write, WriteStorage,
+        },
+        ParentHierarchy,
+    },
+    derive::PrefabData,
+    renderer::{
+        formats::texture::ImageFormat, loaders::load_from_srgba, palette::Srgba, Texture,
+    },
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    antialiasing::Antialiasing,
+    boil::LineBoil,
+    strokes::{StrokeBrush, StrokeStyle},
+};
+
+use std::{fs, io, path::Path};
+
+/// Most light bands `cel_shading` can quantize into.
+pub const MAX_BANDS: usize = 4;
+/// Most colors a palette can snap the final color to.
+pub const MAX_PALETTE: usize = 8;
+/// Directory under the assets directory that style presets are read from.
+pub const PRESET_DIR: &str = "styles";
+
+/// One step of the cel shading: lighting at or below `threshold` brightness is drawn at
+/// `value` brightness.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LightBand {
+    pub threshold: f32,
+    pub value: f32,
+    /// Saturation added to the lighting color within the band.
+    pub saturation: f32,
+}
+
+impl Default for LightBand {
+    fn default() -> Self {
+        LightBand {
+            threshold: 1.0,
+            value: 0.8,
+            saturation: 0.0,
+        }
+    }
+}
+
+/// How the N·V outline of the cel shader is drawn.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OutlineStyle {
+    pub color: [f32; 4],
+    /// Fragments whose N·V is at or below this are part of the outline.
+    pub threshold: f32,
+    /// How much of the shaded color shows through the outline.
+    pub opacity: f32,
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        OutlineStyle {
+            color: [0.0, 0.0, 0.0, 1.0],
+            threshold: 0.15,
+            opacity: 0.0,
+        }
+    }
+}
+
+/// Full-screen effect applied after the scene is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PostEffect {
+    /// Fast approximate anti-aliasing of the cel bands and outlines.
+    Fxaa,
+}
+
+/// Shading parameters of the cel shader, passed to it in `NprUniformArgs`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CelStyle {
+    /// Bands ordered by threshold; the last one catches everything brighter.
+    pub bands: Vec<LightBand>,
+    pub outline: OutlineStyle,
+    /// Colors the shaded result snaps to, all colors allowed when empty.
+    pub palette: Vec<[f32; 3]>,
+    /// Blend between bands instead of stepping, over `band_softness` pixels.
+    pub smooth_bands: bool,
+    /// Width in pixels of a smoothed band edge, before each object's `BandSoftness`.
+    pub band_softness: f32,
+}
+
+impl Default for CelStyle {
+    /// The two-tone shading with a saturated terminator that `outline.frag` always had.
+    fn default() -> Self {
+        CelStyle {
+            bands: vec![
+                LightBand {
+                    threshold: 0.5,
+                    value: 0.2,
+                    saturation: 0.0,
+                },
+                LightBand {
+                    threshold: 0.55,
+                    value: 0.8,
+                    saturation: 0.7,
+                },
+                LightBand {
+                    threshold: 1.0,
+                    value: 0.8,
+                    saturation: 0.0,
+                },
+            ],
+            outline: OutlineStyle::default(),
+            palette: Vec::new(),
+            smooth_bands: false,
+            band_softness: 1.0,
+        }
+    }
+}
+
+impl CelStyle {
+    /// Checks that the style fits the shader: at most `MAX_BANDS` bands with strictly
+    /// increasing thresholds and at most `MAX_PALETTE` palette colors.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.bands.len() > MAX_BANDS {
+            return Err(Error::from_string(format!(
+                "{} light bands, at most {} are supported",
+                self.bands.len(),
+                MAX_BANDS
+            )));
+        }
+        if self.palette.len() > MAX_PALETTE {
+            return Err(Error::from_string(format!(
+                "{} palette colors, at most {} are supported",
+                self.palette.len(),
+                MAX_PALETTE
+            )));
+        }
+        for pair in self.bands.windows(2) {
+            if pair[1].threshold <= pair[0].threshold {
+                return Err(Error::from_string(format!(
+                    "light band thresholds must increase, got {} after {}",
+                    pair[1].threshold, pair[0].threshold
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the widest band in two, up to `MAX_BANDS` bands.
+    pub fn add_band(&mut self) {
+        if self.bands.is_empty() || self.bands.len() >= MAX_BANDS {
+            return;
+        }
+        let widths = self.band_widths();
+        let i = (0..widths.len()).fold(0, |widest, i| {
+            if widths[i] > widths[widest] {
+                i
+            } else {
+                widest
+            }
+        });
+        let below = if i == 0 { 0.0 } else { self.bands[i - 1].value };
+        let band = LightBand {
+            threshold: self.bands[i].threshold - widths[i] / 2.0,
+            value: (below + self.bands[i].value) / 2.0,
+            saturation: 0.0,
+        };
+        self.bands.insert(i, band);
+    }
+
+    /// Removes the narrowest band, widening the one above it, but keeps at least one band.
+    pub fn remove_band(&mut self) {
+        if self.bands.len() <= 1 {
+            return;
+        }
+        // The last band catches everything brighter, so it is never the one removed.
+        let widths = self.band_widths();
+        let i = (0..widths.len() - 1).fold(0, |narrowest, i| {
+            if widths[i] < widths[narrowest] {
+                i
+            } else {
+                narrowest
+            }
+        });
+        self.bands.remove(i);
+    }
+
+    /// Brightness range each band covers.
+    fn band_widths(&self) -> Vec<f32> {
+        let mut lower = 0.0;
+        self.bands
+            .iter()
+            .map(|band| {
+                let width = band.threshold - lower;
+                lower = band.threshold;
+                width
+            })
+            .collect()
+    }
+}
+
+/// Scales the smoothed band edges of an entity and its descendants, so a material can be
+/// crisper or softer than the rest of the style. 0 keeps hard steps.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+pub struct BandSoftness(pub f32);
+
+impl Default for BandSoftness {
+    fn default() -> Self {
+        BandSoftness(1.0)
+    }
+}
+
+impl Component for BandSoftness {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// The `BandSoftness` that applies to a mesh, its own or its nearest ancestor's.
+///
+/// Written by `BandSoftnessSystem` and read per instance by `RenderCustom3D`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InheritedBandSoftness(pub f32);
+
+impl Component for InheritedBandSoftness {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Hands each `BandSoftness` down the hierarchy of its entity.
+#[derive(Debug, Default)]
+pub struct BandSoftnessSystem;
+
+impl<'a> System<'a> for BandSoftnessSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, ParentHierarchy>,
+        ReadStorage<'a, BandSoftness>,
+        WriteStorage<'a, InheritedBandSoftness>,
+    );
+
+    fn run(&mut self, (entities, hierarchy, softnesses, mut inherited): Self::SystemData) {
+        inherited.clear();
+        for (entity, softness) in (&*entities, &softnesses).join() {
+            let _ = inherited.insert(entity, InheritedBandSoftness(softness.0));
+        }
+        // Parents are sorted before their children, so the nearest ancestor wins.
+        for &entity in hierarchy.all() {
+            if softnesses.contains(entity) {
+                continue;
+            }
+            let parent = hierarchy.parent(entity).and_then(|p| inherited.get(p)).copied();
+            if let Some(softness) = parent {
+                let _ = inherited.insert(entity, softness);
+            }
+        }
+    }
+}
+
+/// A complete NPR look, loaded from a RON file under `assets/styles/` through the `Loader`
+/// and hot-reloaded when the file changes.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StylePreset {
+    pub cel: CelStyle,
+    pub strokes: StrokeStyle,
+    pub boil: LineBoil,
+    /// Post effects, applied in order.
+    pub post_effects: Vec<PostEffect>,
+}
+
+impl Asset for StylePreset {
+    const NAME: &'static str = "npr_app::StylePreset";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+/// The post effects chain of the active preset.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PostEffects(pub Vec<PostEffect>);
+
+/// Every style preset found under `assets/styles/`, and which one is applied.
+#[derive(Clone, Debug, Default)]
+pub struct StylePresets {
+    /// Preset names, the file stems, with their handles in the same order.
+    pub names: Vec<String>,
+    pub handles: Vec<Handle<StylePreset>>,
+    pub active: usize,
+    /// Version of the active preset last applied, so reloads are picked up.
+    applied: Option<(usize, u32)>,
+}
+
+impl StylePresets {
+    /// Starts loading every preset under `assets_dir/styles`, sorted by name.
+    pub fn load(
+        assets_dir: &Path,
+        loader: &Loader,
+        storage: &AssetStorage<StylePreset>,
+    ) -> io::Result<Self> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(assets_dir.join(PRESET_DIR))? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |e| e == "ron") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+        names.sort();
+        let handles = names
+            .iter()
+            .map(|name| {
+                let path = format!("{}/{}.ron", PRESET_DIR, name);
+                loader.load(path, RonFormat, (), storage)
+            })
+            .collect();
+        Ok(StylePresets {
+            names,
+            handles,
+            active: 0,
+            applied: None,
+        })
+    }
+
+    /// Activates the preset at `index`, returning its name.
+    pub fn select(&mut self, index: usize) -> Option<&str> {
+        if index >= self.names.len() {
+            return None;
+        }
+        self.active = index;
+        self.active_name()
+    }
+
+    /// Activates the preset called `name`.
+    pub fn select_name(&mut self, name: &str) -> bool {
+        match self.names.iter().position(|n| n == name) {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn active_name(&self) -> Option<&str> {
+        self.names.get(self.active).map(String::as_str)
+    }
+}
+
+/// Applies the active `StylePreset` to the cel shader, strokes and line boil whenever a
+/// different preset is selected or its file is reloaded. Presets that fail
+/// `CelStyle::validate` are logged and skipped.
+#[derive(Debug, Default)]
+pub struct StylePresetSystem;
+
+impl<'a> System<'a> for StylePresetSystem {
+    type SystemData = (
+        Write<'a, StylePresets>,
+        Read<'a, AssetStorage<StylePreset>>,
+        Write<'a, CelStyle>,
+        Write<'a, StrokeStyle>,
+        Write<'a, LineBoil>,
+        Write<'a, PostEffects>,
+        Read<'a, Antialiasing>,
+        Option<Write<'a, StrokeBrush>>,
+        AssetLoaderSystemData<'a, Texture>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut presets,
+            storage,
+            mut cel,
+            mut strokes,
+            mut boil,
+            mut post_effects,
+            antialiasing,
+            brush,
+            textures,
+        ): Self::SystemData,
+    ) {
+        let active = presets.active;
+        let handle = match presets.handles.get(active) {
+            Some(handle) => handle,
+            None => return,
+        };
+        let (preset, version) = match (storage.get(handle), storage.get_version(handle)) {
+            (Some(preset), Some(version)) => (preset, version),
+            _ => return,
+        };
+        if presets.applied == Some((active, version)) {
+            return;
+        }
+        presets.applied = Some((active, version));
+        // Keep the current look rather than silently dropping bands or colors.
+        if let Err(e) = preset.cel.validate() {
+            log::error!(
+                "Style preset `{}` not applied: {}",
+                presets.active_name().unwrap_or_default(),
+                e
+            );
+            return;
+        }
+
+        if let Some(mut brush) = brush {
+            if preset.strokes.brush != strokes.brush {
+                brush.0 = match &preset.strokes.brush {
+                    Some(path) => textures.load(path.as_str(), ImageFormat::default(), ()),
+                    None => textures.load_from_data(
+                        load_from_srgba(Srgba::new(1.0, 1.0, 1.0, 1.0)).into(),
+                        (),
+                    ),
+                };
+            }
+        }
+        *cel = preset.cel.clone();
+        *strokes = preset.strokes.clone();
+        // Keep the boil running from the same seed so switching presets does not jump.
+        let seed = boil.seed;
+        *boil = LineBoil {
+            seed,
+            ..preset.boil.clone()
+        };
+        post_effects.0 = preset.post_effects.clone();
+        if antialiasing.fxaa && !post_effects.0.contains(&PostEffect::Fxaa) {
+            post_effects.0.push(PostEffect::Fxaa);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn band(threshold: f32) -> LightBand {
+        LightBand {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_style_is_valid() {
+        assert!(CelStyle::default().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_too_many_bands() {
+        let style = CelStyle {
+            bands: (1..=MAX_BANDS + 1).map(|i| band(i as f32 * 0.1)).collect(),
+            ..Default::default()
+        };
+        assert!(style.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_too_many_palette_colors() {
+        let style = CelStyle {
+            palette: vec![[0.5, 0.5, 0.5]; MAX_PALETTE + 1],
+            ..Default::default()
+        };
+        assert!(style.validate().is_err());
+        let style = CelStyle {
+            palette: vec![[0.5, 0.5, 0.5]; MAX_PALETTE],
+            ..Default::default()
+        };
+        assert!(style.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_unsorted_thresholds() {
+        let style = CelStyle {
+            bands: vec![band(0.6), band(0.3), band(1.0)],
+            ..Default::default()
+        };
+        assert!(style.validate().is_err());
+        let style = CelStyle {
+            bands: vec![band(0.5), band(0.5)],
+            ..Default::default()
+        };
+        assert!(style.validate().is_err());
+    }
+
+    #[test]
+    fn adds_and_removes_bands() {
+        let mut style = CelStyle {
+            bands: vec![band(0.2), band(1.0)],
+            ..Default::default()
+        };
+        style.add_band();
+        assert_eq!(
+            style.bands.iter().map(|b| b.threshold).collect::<Vec<_>>(),
+            vec![0.2, 0.6, 1.0]
+        );
+        style.add_band();
+        style.add_band();
+        assert_eq!(style.bands.len(), MAX_BANDS);
+        assert!(style.validate().is_ok());
+
+        style.remove_band();
+        assert_eq!(style.bands.len(), MAX_BANDS - 1);
+        for _ in 0..MAX_BANDS {
+            style.remove_band();
+        }
+        assert_eq!(style.bands, vec![band(1.0)]);
+    }
+}
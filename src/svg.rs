@@ -0,0 +1,637 @@
+use amethyst::core::math::{Matrix4, Point2, Point3, Vector4};
+
+use crate::lines::{EdgeKind, FeatureEdge, LineMesh, LineSettings, Viewpoint};
+
+use std::{collections::HashMap, fmt::Write as _};
+
+/// Appearance and precision settings for the SVG exporter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgStyle {
+    pub silhouette_width: f32,
+    pub crease_width: f32,
+    pub boundary_width: f32,
+    pub stroke_color: String,
+    /// Optional background fill, omitted from the file when `None`.
+    pub background: Option<String>,
+    /// Depth tolerance used when testing lines against the depth buffer.
+    pub depth_bias: f32,
+}
+
+impl Default for SvgStyle {
+    fn default() -> Self {
+        SvgStyle {
+            silhouette_width: 2.5,
+            crease_width: 1.5,
+            boundary_width: 2.0,
+            stroke_color: "#000000".to_string(),
+            background: None,
+            depth_bias: 2.0e-3,
+        }
+    }
+}
+
+impl SvgStyle {
+    fn width(&self, kind: EdgeKind) -> f32 {
+        match kind {
+            EdgeKind::Silhouette => self.silhouette_width,
+            EdgeKind::Crease => self.crease_width,
+            EdgeKind::Boundary => self.boundary_width,
+        }
+    }
+}
+
+/// A chain of connected screen-space points sharing a single feature kind.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline {
+    pub kind: EdgeKind,
+    pub points: Vec<Point2<f32>>,
+}
+
+/// A projected point: pixel coordinates plus a depth where smaller means closer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ScreenPoint {
+    x: f32,
+    y: f32,
+    depth: f32,
+}
+
+/// Projects world positions to pixels through a camera's view-projection matrix.
+#[derive(Clone, Debug)]
+pub struct ScreenProjection {
+    view_proj: Matrix4<f32>,
+    width: f32,
+    height: f32,
+    // Amethyst uses a reversed depth range, so NDC depth is flipped to keep "smaller is closer".
+    depth_sign: f32,
+}
+
+impl ScreenProjection {
+    /// `proj` is the camera projection matrix, `view` the inverse of the camera's global matrix.
+    pub fn new(proj: &Matrix4<f32>, view: &Matrix4<f32>, width: f32, height: f32) -> Self {
+        let near = proj * Vector4::new(0.0, 0.0, -1.0, 1.0);
+        let far = proj * Vector4::new(0.0, 0.0, -2.0, 1.0);
+        let depth_sign = if far.z / far.w >= near.z / near.w {
+            1.0
+        } else {
+            -1.0
+        };
+        ScreenProjection {
+            view_proj: proj * view,
+            width,
+            height,
+            depth_sign,
+        }
+    }
+
+    fn clip(&self, point: &Point3<f32>) -> Vector4<f32> {
+        self.view_proj * point.to_homogeneous()
+    }
+
+    fn to_screen(&self, clip: &Vector4<f32>) -> ScreenPoint {
+        // Amethyst projections already flip Y, so NDC maps directly onto SVG's downward Y axis.
+        ScreenPoint {
+            x: (clip.x / clip.w + 1.0) * 0.5 * self.width,
+            y: (clip.y / clip.w + 1.0) * 0.5 * self.height,
+            depth: self.depth_sign * clip.z / clip.w,
+        }
+    }
+
+    /// Projects a segment, clipping it against the near plane.
+    fn segment(&self, a: &Point3<f32>, b: &Point3<f32>) -> Option<(ScreenPoint, ScreenPoint)> {
+        const MIN_W: f32 = 1.0e-4;
+        let (mut ca, mut cb) = (self.clip(a), self.clip(b));
+        if ca.w < MIN_W && cb.w < MIN_W {
+            return None;
+        }
+        if ca.w < MIN_W {
+            let t = (MIN_W - ca.w) / (cb.w - ca.w);
+            ca += (cb - ca) * t;
+        } else if cb.w < MIN_W {
+            let t = (MIN_W - cb.w) / (ca.w - cb.w);
+            cb += (ca - cb) * t;
+        }
+        Some((self.to_screen(&ca), self.to_screen(&cb)))
+    }
+}
+
+/// CPU depth buffer of the scene's triangles, used for hidden-line removal.
+#[derive(Clone, Debug)]
+pub struct DepthBuffer {
+    width: usize,
+    height: usize,
+    depth: Vec<f32>,
+}
+
+impl DepthBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        DepthBuffer {
+            width,
+            height,
+            depth: vec![std::f32::INFINITY; width * height],
+        }
+    }
+
+    /// Rasterizes every triangle of `mesh`, which must already be in world space.
+    pub fn rasterize(&mut self, mesh: &LineMesh, projection: &ScreenProjection) {
+        let clipped = mesh
+            .positions()
+            .iter()
+            .map(|p| projection.clip(p))
+            .collect::<Vec<_>>();
+        for tri in mesh.triangles() {
+            let clip = [
+                clipped[tri[0] as usize],
+                clipped[tri[1] as usize],
+                clipped[tri[2] as usize],
+            ];
+            // Triangles crossing the near plane are rare for framed models; skip them
+            // rather than clipping, which at worst lets a few hidden lines through.
+            if clip.iter().any(|c| c.w <= 0.0) {
+                continue;
+            }
+            let s = [
+                projection.to_screen(&clip[0]),
+                projection.to_screen(&clip[1]),
+                projection.to_screen(&clip[2]),
+            ];
+            self.triangle(&s);
+        }
+    }
+
+    fn triangle(&mut self, s: &[ScreenPoint; 3]) {
+        let area = edge_function(&s[0], &s[1], s[2].x, s[2].y);
+        if area.abs() <= std::f32::EPSILON {
+            return;
+        }
+        let min_x = s.iter().map(|p| p.x).fold(std::f32::INFINITY, f32::min).floor().max(0.0) as usize;
+        let min_y = s.iter().map(|p| p.y).fold(std::f32::INFINITY, f32::min).floor().max(0.0) as usize;
+        let max_x = (s.iter().map(|p| p.x).fold(std::f32::NEG_INFINITY, f32::max).ceil() as usize)
+            .min(self.width);
+        let max_y = (s.iter().map(|p| p.y).fold(std::f32::NEG_INFINITY, f32::max).ceil() as usize)
+            .min(self.height);
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = edge_function(&s[1], &s[2], px, py) / area;
+                let w1 = edge_function(&s[2], &s[0], px, py) / area;
+                let w2 = 1.0 - w0 - w1;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+                // NDC depth is affine in screen space, so plain barycentrics are exact.
+                let depth = w0 * s[0].depth + w1 * s[1].depth + w2 * s[2].depth;
+                let slot = &mut self.depth[y * self.width + x];
+                if depth < *slot {
+                    *slot = depth;
+                }
+            }
+        }
+    }
+
+    /// Depth of the farthest surface in the 3x3 neighbourhood, tolerating the one pixel
+    /// offset between a line and the triangles it borders.
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let (cx, cy) = (x.floor() as isize, y.floor() as isize);
+        let mut depth = std::f32::NEG_INFINITY;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (sx, sy) = (cx + dx, cy + dy);
+                if sx < 0 || sy < 0 || sx >= self.width as isize || sy >= self.height as isize {
+                    continue;
+                }
+                depth = depth.max(self.depth[sy as usize * self.width + sx as usize]);
+            }
+        }
+        depth
+    }
+}
+
+fn edge_function(a: &ScreenPoint, b: &ScreenPoint, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+/// Splits a projected feature edge into its visible screen-space pieces.
+fn visible_pieces(
+    a: ScreenPoint,
+    b: ScreenPoint,
+    kind: EdgeKind,
+    depth: &DepthBuffer,
+    bias: f32,
+    out: &mut Vec<Polyline>,
+) {
+    let length = ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt();
+    let steps = (length.ceil() as usize).max(1);
+    let mut start = None;
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let (x, y) = (a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+        let z = a.depth + (b.depth - a.depth) * t;
+        let visible = z <= depth.sample(x, y) + bias;
+        match (visible, start) {
+            (true, None) => start = Some(Point2::new(x, y)),
+            (false, Some(from)) => {
+                let prev = (i - 1) as f32 / steps as f32;
+                let to = Point2::new(a.x + (b.x - a.x) * prev, a.y + (b.y - a.y) * prev);
+                if from != to {
+                    out.push(Polyline {
+                        kind,
+                        points: vec![from, to],
+                    });
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        let to = Point2::new(b.x, b.y);
+        if from != to {
+            out.push(Polyline {
+                kind,
+                points: vec![from, to],
+            });
+        }
+    }
+}
+
+/// Projects feature edges to the screen and keeps only the parts not hidden by `depth`.
+pub fn visible_segments(
+    edges: &[FeatureEdge],
+    projection: &ScreenProjection,
+    depth: &DepthBuffer,
+    bias: f32,
+) -> Vec<Polyline> {
+    let mut segments = Vec::new();
+    for edge in edges {
+        if let Some((a, b)) = projection.segment(&edge.a, &edge.b) {
+            visible_pieces(a, b, edge.kind, depth, bias, &mut segments);
+        }
+    }
+    segments
+}
+
+/// Joins two-point segments that share endpoints and kind into longer polylines.
+pub fn chain_segments(segments: &[Polyline]) -> Vec<Polyline> {
+    // Endpoints are snapped to a tenth of a pixel so shared mesh vertices match exactly.
+    let key = |p: &Point2<f32>| ((p.x * 10.0).round() as i64, (p.y * 10.0).round() as i64);
+
+    let mut endpoints: HashMap<(EdgeKind, (i64, i64)), Vec<usize>> = HashMap::new();
+    for (i, segment) in segments.iter().enumerate() {
+        for p in &[segment.points[0], *segment.points.last().unwrap()] {
+            endpoints.entry((segment.kind, key(p))).or_default().push(i);
+        }
+    }
+
+    let mut used = vec![false; segments.len()];
+    let next_from = |point: &Point2<f32>, kind: EdgeKind, used: &mut Vec<bool>| {
+        let candidates = endpoints.get(&(kind, key(point)))?;
+        let next = *candidates.iter().find(|&&i| !used[i])?;
+        used[next] = true;
+        let s = &segments[next].points;
+        if key(&s[0]) == key(point) {
+            Some(s[1..].to_vec())
+        } else {
+            Some(s[..s.len() - 1].iter().rev().cloned().collect())
+        }
+    };
+
+    let mut polylines = Vec::new();
+    for i in 0..segments.len() {
+        if used[i] {
+            continue;
+        }
+        used[i] = true;
+        let kind = segments[i].kind;
+        let mut points = segments[i].points.clone();
+        while let Some(more) = next_from(points.last().unwrap(), kind, &mut used) {
+            points.extend(more);
+        }
+        points.reverse();
+        while let Some(more) = next_from(points.last().unwrap(), kind, &mut used) {
+            points.extend(more);
+        }
+        polylines.push(Polyline { kind, points });
+    }
+    polylines
+}
+
+/// Serializes polylines as an SVG document of the given pixel size.
+pub fn write_svg(polylines: &[Polyline], width: f32, height: f32, style: &SvgStyle) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    );
+    if let Some(background) = &style.background {
+        let _ = writeln!(
+            svg,
+            r#"  <rect width="100%" height="100%" fill="{}"/>"#,
+            escape_attribute(background)
+        );
+    }
+    for &kind in &[EdgeKind::Boundary, EdgeKind::Crease, EdgeKind::Silhouette] {
+        let _ = writeln!(
+            svg,
+            r#"  <g id="{:?}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round">"#,
+            kind,
+            escape_attribute(&style.stroke_color),
+            style.width(kind)
+        );
+        for polyline in polylines.iter().filter(|p| p.kind == kind) {
+            let points = polyline
+                .points
+                .iter()
+                .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(svg, r#"    <polyline points="{}"/>"#, points);
+        }
+        let _ = writeln!(svg, "  </g>");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Escapes the characters that would end or break a double-quoted XML attribute value.
+fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Runs the full export pipeline on world-space meshes, each extracted with its own
+/// settings, and returns the SVG document.
+///
+/// `camera_global` is the camera entity's global matrix and `proj` its projection matrix.
+pub fn render_line_drawing(
+    meshes: &[(LineMesh, LineSettings)],
+    proj: &Matrix4<f32>,
+    camera_global: &Matrix4<f32>,
+    orthographic: bool,
+    (width, height): (u32, u32),
+    style: &SvgStyle,
+) -> String {
+    let view = camera_global
+        .try_inverse()
+        .unwrap_or_else(Matrix4::identity);
+    let projection = ScreenProjection::new(proj, &view, width as f32, height as f32);
+    let viewpoint = Viewpoint::from_camera_matrix(camera_global, orthographic);
+
+    let mut depth = DepthBuffer::new(width as usize, height as usize);
+    for (mesh, _) in meshes {
+        depth.rasterize(mesh, &projection);
+    }
+
+    let mut segments = Vec::new();
+    for (mesh, settings) in meshes {
+        let edges = mesh.extract(&viewpoint, settings);
+        segments.extend(visible_segments(&edges, &projection, &depth, style.depth_bias));
+    }
+    write_svg(
+        &chain_segments(&segments),
+        width as f32,
+        height as f32,
+        style,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: f32 = 100.0;
+
+    // Identity view and projection: NDC equals world space, looking down -Z, and larger Z is
+    // closer to the camera.
+    fn projection() -> ScreenProjection {
+        ScreenProjection::new(&Matrix4::identity(), &Matrix4::identity(), SIZE, SIZE)
+    }
+
+    fn quad(min: [f32; 2], max: [f32; 2], z: f32) -> LineMesh {
+        LineMesh::new(
+            &[
+                [min[0], min[1], z],
+                [max[0], min[1], z],
+                [max[0], max[1], z],
+                [min[0], max[1], z],
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        )
+    }
+
+    fn segment(kind: EdgeKind, a: [f32; 2], b: [f32; 2]) -> Polyline {
+        Polyline {
+            kind,
+            points: vec![Point2::new(a[0], a[1]), Point2::new(b[0], b[1])],
+        }
+    }
+
+    #[test]
+    fn projection_maps_ndc_to_pixels() {
+        let projection = projection();
+        let center = projection.to_screen(&projection.clip(&Point3::new(0.0, 0.0, 0.0)));
+        assert_eq!((center.x, center.y), (50.0, 50.0));
+        let corner = projection.to_screen(&projection.clip(&Point3::new(1.0, -1.0, 0.0)));
+        assert_eq!((corner.x, corner.y), (100.0, 0.0));
+
+        let near = projection.to_screen(&projection.clip(&Point3::new(0.0, 0.0, 0.5)));
+        let far = projection.to_screen(&projection.clip(&Point3::new(0.0, 0.0, -0.5)));
+        assert!(near.depth < far.depth);
+    }
+
+    #[test]
+    fn segment_is_clipped_to_the_near_plane() {
+        // A perspective-like matrix that copies -Z into W, so points with Z >= 0 are behind.
+        let mut proj = Matrix4::identity();
+        proj[(3, 3)] = 0.0;
+        proj[(3, 2)] = -1.0;
+        let projection = ScreenProjection::new(&proj, &Matrix4::identity(), SIZE, SIZE);
+        let behind = (Point3::new(0.0, 0.0, 1.0), Point3::new(1.0, 0.0, 0.5));
+        assert!(projection.segment(&behind.0, &behind.1).is_none());
+        assert!(projection
+            .segment(&Point3::new(0.0, 0.0, 1.0), &Point3::new(0.0, 0.0, -1.0))
+            .is_some());
+    }
+
+    #[test]
+    fn depth_buffer_keeps_the_closest_surface() {
+        let projection = projection();
+        let mut depth = DepthBuffer::new(SIZE as usize, SIZE as usize);
+        depth.rasterize(&quad([-0.5, -0.5], [0.5, 0.5], -0.5), &projection);
+        depth.rasterize(&quad([-0.5, -0.5], [0.5, 0.5], 0.5), &projection);
+
+        assert!((depth.sample(50.0, 50.0) - -0.5).abs() < 1.0e-5);
+        assert_eq!(depth.sample(10.0, 10.0), std::f32::INFINITY);
+        // Next to the quad's border the farthest neighbour is the empty background.
+        assert_eq!(depth.sample(25.0, 50.0), std::f32::INFINITY);
+    }
+
+    #[test]
+    fn edge_behind_a_quad_is_split_around_it() {
+        let projection = projection();
+        let mut depth = DepthBuffer::new(SIZE as usize, SIZE as usize);
+        depth.rasterize(&quad([-0.5, -1.0], [0.5, 1.0], 0.5), &projection);
+
+        let edge = FeatureEdge {
+            a: Point3::new(-1.0, 0.0, 0.0),
+            b: Point3::new(1.0, 0.0, 0.0),
+            kind: EdgeKind::Crease,
+        };
+        let pieces = visible_segments(&[edge], &projection, &depth, 1.0e-3);
+        assert_eq!(pieces.len(), 2);
+        let (left, right) = (&pieces[0].points, &pieces[1].points);
+        assert_eq!(left[0], Point2::new(0.0, 50.0));
+        assert!((left[1].x - 25.0).abs() <= 2.0);
+        assert!((right[0].x - 75.0).abs() <= 2.0);
+        assert_eq!(right[1], Point2::new(100.0, 50.0));
+
+        // The same edge in front of the quad is not hidden at all.
+        let edge = FeatureEdge {
+            a: Point3::new(-1.0, 0.0, 0.9),
+            b: Point3::new(1.0, 0.0, 0.9),
+            kind: EdgeKind::Crease,
+        };
+        let pieces = visible_segments(&[edge], &projection, &depth, 1.0e-3);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(
+            pieces[0].points,
+            vec![Point2::new(0.0, 50.0), Point2::new(100.0, 50.0)]
+        );
+    }
+
+    #[test]
+    fn chains_a_closed_loop() {
+        // A square given out of order and with mixed directions.
+        let segments = [
+            segment(EdgeKind::Silhouette, [10.0, 10.0], [20.0, 10.0]),
+            segment(EdgeKind::Silhouette, [10.0, 20.0], [20.0, 20.0]),
+            segment(EdgeKind::Silhouette, [20.0, 20.0], [20.0, 10.0]),
+            segment(EdgeKind::Silhouette, [10.0, 20.0], [10.0, 10.0]),
+        ];
+        let polylines = chain_segments(&segments);
+        assert_eq!(polylines.len(), 1);
+        let points = &polylines[0].points;
+        assert_eq!(points.len(), 5);
+        assert_eq!(points.first(), points.last());
+    }
+
+    #[test]
+    fn chains_an_open_polyline_by_kind() {
+        let segments = [
+            segment(EdgeKind::Crease, [20.0, 0.0], [30.0, 5.0]),
+            segment(EdgeKind::Crease, [0.0, 0.0], [10.0, 0.0]),
+            segment(EdgeKind::Crease, [20.0, 0.0], [10.0, 0.0]),
+            // Shares an endpoint but not the kind, so it stays separate.
+            segment(EdgeKind::Boundary, [30.0, 5.0], [40.0, 5.0]),
+        ];
+        let mut polylines = chain_segments(&segments);
+        polylines.sort_by_key(|p| p.points.len());
+        assert_eq!(polylines.len(), 2);
+        assert_eq!(polylines[0].kind, EdgeKind::Boundary);
+
+        let crease = &polylines[1];
+        assert_eq!(crease.kind, EdgeKind::Crease);
+        let mut points = crease.points.clone();
+        if points[0].x > points[3].x {
+            points.reverse();
+        }
+        assert_eq!(
+            points,
+            vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(10.0, 0.0),
+                Point2::new(20.0, 0.0),
+                Point2::new(30.0, 5.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_known_svg() {
+        let style = SvgStyle {
+            background: Some("#ffffff".to_string()),
+            ..Default::default()
+        };
+        let polylines = [
+            segment(EdgeKind::Silhouette, [0.0, 0.0], [10.5, 20.25]),
+            segment(EdgeKind::Boundary, [1.0, 2.0], [3.0, 4.0]),
+        ];
+        let expected = r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="32" viewBox="0 0 64 32">
+  <rect width="100%" height="100%" fill="#ffffff"/>
+  <g id="Boundary" fill="none" stroke="#000000" stroke-width="2" stroke-linecap="round" stroke-linejoin="round">
+    <polyline points="1.00,2.00 3.00,4.00"/>
+  </g>
+  <g id="Crease" fill="none" stroke="#000000" stroke-width="1.5" stroke-linecap="round" stroke-linejoin="round">
+  </g>
+  <g id="Silhouette" fill="none" stroke="#000000" stroke-width="2.5" stroke-linecap="round" stroke-linejoin="round">
+    <polyline points="0.00,0.00 10.50,20.25"/>
+  </g>
+</svg>
+"##;
+        assert_eq!(write_svg(&polylines, 64.0, 32.0, &style), expected);
+    }
+
+    #[test]
+    fn escapes_colors_in_attributes() {
+        let style = SvgStyle {
+            stroke_color: r#"red" onload="alert(1)"#.to_string(),
+            background: Some("<&>".to_string()),
+            ..Default::default()
+        };
+        let svg = write_svg(&[], 8.0, 8.0, &style);
+        assert!(svg.contains(r#"fill="&lt;&amp;&gt;""#));
+        assert!(svg.contains(r#"stroke="red&quot; onload=&quot;alert(1)""#));
+        assert!(!svg.contains(r#"onload="alert"#));
+    }
+
+    #[test]
+    fn renders_the_outline_of_a_quad() {
+        let settings = LineSettings {
+            crease_angle: 40.0,
+            silhouettes: true,
+            creases: true,
+            boundaries: true,
+            cull_back_faces: true,
+        };
+        let svg = render_line_drawing(
+            &[(quad([-0.5, -0.5], [0.5, 0.5], 0.0), settings)],
+            &Matrix4::identity(),
+            &Matrix4::identity(),
+            true,
+            (100, 100),
+            &SvgStyle::default(),
+        );
+        assert_eq!(svg.matches("<polyline").count(), 1);
+        assert!(svg.contains(r#"<g id="Boundary""#));
+        let boundary = svg.split(r#"<g id="Crease""#).next().unwrap();
+        assert!(boundary.contains("<polyline"));
+
+        // With boundaries disabled a lone flat quad has no feature lines left.
+        let svg = render_line_drawing(
+            &[(
+                quad([-0.5, -0.5], [0.5, 0.5], 0.0),
+                LineSettings {
+                    boundaries: false,
+                    ..settings
+                },
+            )],
+            &Matrix4::identity(),
+            &Matrix4::identity(),
+            true,
+            (100, 100),
+            &SvgStyle::default(),
+        );
+        assert_eq!(svg.matches("<polyline").count(), 0);
+    }
+}